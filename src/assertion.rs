@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+
+use super::*;
+
+#[derive(Clone, Copy)]
+pub enum AssertKind {
+    Single,
+    Exists,
+    Distinct,
+}
+
+impl AssertKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AssertKind::Single => "assert_single",
+            AssertKind::Exists => "assert_exists",
+            AssertKind::Distinct => "assert_distinct",
+        }
+    }
+}
+
+/// `assert_single((expr), message := '...')`
+#[derive(Clone)]
+pub struct Assert<'a> {
+    kind: AssertKind,
+    expr: Arc<dyn ToQuery + 'a>,
+    message: Option<Cow<'a, str>>,
+}
+
+impl<'a> Assert<'a> {
+    pub fn new<T>(kind: AssertKind, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self {
+            kind,
+            expr: Arc::new(expr),
+            message: None,
+        }
+    }
+
+    /// error message on violation
+    pub fn message(mut self, message: impl Into<Cow<'a, str>>) -> Self {
+        self.message.replace(message.into());
+
+        self
+    }
+}
+
+impl<'a> ToQuery for Assert<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let mut args = vec![(None, ast::Expr::Paren(Box::new(self.expr.to_ast())))];
+
+        if let Some(message) = &self.message {
+            args.push((
+                Some("message".into()),
+                ast::Expr::Literal(message.to_query_arg().into()),
+            ));
+        }
+
+        ast::Expr::Call(Box::new(ast::Call {
+            name: self.kind.as_str().into(),
+            args,
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.expr.validate()
+    }
+}
+
+/// ```ignore
+/// select("User", fields! { name })
+///     .filter(filter().add(AND, ".email = $?", email))
+///     .assert_single()
+///     .message("duplicated email")
+///     .query_required_single::<User>(&client)
+///     .await?;
+/// ```
+pub trait AssertCardinality<'a>: ToQuery + Sized + 'a {
+    fn assert_single(self) -> Assert<'a> {
+        Assert::new(AssertKind::Single, self)
+    }
+
+    fn assert_exists(self) -> Assert<'a> {
+        Assert::new(AssertKind::Exists, self)
+    }
+
+    fn assert_distinct(self) -> Assert<'a> {
+        Assert::new(AssertKind::Distinct, self)
+    }
+}
+
+impl<'a, T> AssertCardinality<'a> for T where T: ToQuery + 'a {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let query = select("User", crate::fields! { name })
+            .filter(filter().add(AND, ".email = $?", "it's@me"))
+            .assert_single()
+            .message("duplicated email")
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+assert_single(
+  (
+    select User {
+      name,
+    }
+    filter
+      .email = <str>'it\\'s@me'
+  ),
+  message := <str>'duplicated email'
+)"
+        );
+
+        let query = raw("User").assert_exists().to_query();
+
+        assert_eq!(
+            query,
+            "\
+assert_exists(
+  (
+    User
+  )
+)"
+        );
+    }
+}
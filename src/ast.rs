@@ -0,0 +1,658 @@
+//! EdgeQL syntax tree made by query builders
+//!
+//! every builder lowers itself into `Expr` by `ToQuery::to_ast`, and `printer` renders it.
+//! passes such as `hoist` rewrite the tree instead of the rendered string.
+
+use std::borrow::Cow;
+
+use edgedb_protocol::value::Value;
+use either::Either;
+
+use crate::{AndOr, Assign, OrderDirection, SetOperator};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr<'a> {
+    /// names, paths and raw EdgeQL as-is
+    Raw(Cow<'a, str>),
+    /// rendered `ToQueryArg`
+    Literal(Cow<'a, str>),
+    /// `<ty>$name`, value passed as query argument
+    Param(Box<Param<'a>>),
+    /// `(expr)` on its own lines
+    Paren(Box<Expr<'a>>),
+    Select(Box<Select<'a>>),
+    Insert(Box<Insert<'a>>),
+    Update(Box<Update<'a>>),
+    Delete(Box<Delete<'a>>),
+    Group(Box<Group<'a>>),
+    For(Box<For<'a>>),
+    Condition(Box<Condition<'a>>),
+    IfElse(Box<IfElse<'a>>),
+    /// `a ?? b`
+    Coalesce(Vec<Expr<'a>>),
+    /// `exists (expr)`, `distinct (expr)`
+    Prefix(Cow<'a, str>, Box<Expr<'a>>),
+    Call(Box<Call<'a>>),
+    Set(Box<Set<'a>>),
+    /// shape element rendered alone
+    ShapeElement(Box<ShapeElement<'a>>),
+    /// with item rendered alone
+    WithItem(Box<WithItem<'a>>),
+    /// `unless conflict` clause rendered alone
+    UnlessConflict(Box<UnlessConflict<'a>>),
+    /// written one after another. e.g. a literal split at template slots
+    Seq(Vec<Expr<'a>>),
+    /// text of a `Template` rendered once, with its bound slots in between
+    Rendered(Box<Rendered<'a>>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WithItem<'a> {
+    /// `name := value`
+    Binding { name: Cow<'a, str>, value: Expr<'a> },
+    /// `[alias as] module module`
+    Module {
+        alias: Option<Cow<'a, str>>,
+        module: Cow<'a, str>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShapeElement<'a> {
+    Splat,
+    DoubleSplat,
+    /// `name` or `name: { shape }`
+    Field {
+        name: Cow<'a, str>,
+        shape: Vec<ShapeElement<'a>>,
+    },
+    /// `name := (expr)`
+    Computed {
+        name: Cow<'a, str>,
+        expr: Expr<'a>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param<'a> {
+    pub name: Cow<'a, str>,
+    /// EdgeDB type of the cast. e.g. `str`. an array is written as `array_unpack(..)`
+    pub ty: Cow<'static, str>,
+    pub value: Value,
+}
+
+/// text of a template in each layout, split at its slots. rendered at indent 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct Text {
+    pub pretty: Vec<String>,
+    pub compact: Vec<String>,
+    pub redacted: Vec<String>,
+    /// parameters already in the text. e.g. of a template bound inside
+    pub params: Vec<Param<'static>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rendered<'a> {
+    pub text: Cow<'a, Text>,
+    /// written after each part of the text. the name alone if the slot is not bound
+    pub slots: Vec<Either<Cow<'a, str>, Param<'a>>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Order<'a> {
+    pub by: Expr<'a>,
+    pub direction: Option<OrderDirection>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Select<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub distinct: bool,
+    pub subject: Expr<'a>,
+    pub shape: Vec<ShapeElement<'a>>,
+    pub filter: Option<Condition<'a>>,
+    pub orders: Vec<Order<'a>>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl<'a> Select<'a> {
+    pub fn new(subject: Expr<'a>) -> Self {
+        Self {
+            withs: Vec::new(),
+            distinct: false,
+            subject,
+            shape: Vec::new(),
+            filter: None,
+            orders: Vec::new(),
+            offset: None,
+            limit: None,
+        }
+    }
+}
+
+/// `name := value` in `set { ... }` or `insert Type { ... }`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment<'a> {
+    pub name: Cow<'a, str>,
+    pub assign: Assign,
+    pub value: Expr<'a>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnlessConflict<'a> {
+    pub on: Option<Cow<'a, str>>,
+    pub else_expr: Option<Expr<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub subject: Cow<'a, str>,
+    pub assignments: Vec<Assignment<'a>>,
+    pub unless_conflict: Option<UnlessConflict<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub subject: Expr<'a>,
+    pub filter: Option<Condition<'a>>,
+    pub assignments: Vec<Assignment<'a>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delete<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub subject: Cow<'a, str>,
+    pub filter: Option<Condition<'a>>,
+    pub orders: Vec<Order<'a>>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub subject: Cow<'a, str>,
+    pub shape: Option<Cow<'a, str>>,
+    pub using: Vec<Cow<'a, str>>,
+    pub by: Vec<Cow<'a, str>>,
+}
+
+/// `for binding in set union (body)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct For<'a> {
+    pub withs: Vec<WithItem<'a>>,
+    pub binding: Cow<'a, str>,
+    pub set: Expr<'a>,
+    pub body: Expr<'a>,
+}
+
+/// `[not] [func](a and b or (c and d))`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Condition<'a> {
+    pub not: bool,
+    pub func: Option<Cow<'a, str>>,
+    pub terms: Vec<(AndOr, Term<'a>)>,
+}
+
+impl<'a> Condition<'a> {
+    pub fn is_parenthesized(&self) -> bool {
+        self.not || self.func.is_some()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term<'a> {
+    Raw(Cow<'a, str>),
+    /// `raw` with the first `$?` replaced by `arg`
+    Arg {
+        raw: Cow<'a, str>,
+        arg: Expr<'a>,
+    },
+    Expr(Expr<'a>),
+    Group(Condition<'a>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfElse<'a> {
+    /// `if cond then x else y` instead of `x if cond else y`
+    pub statement: bool,
+    pub branches: Vec<(Condition<'a>, Expr<'a>)>,
+    pub otherwise: Option<Expr<'a>>,
+}
+
+/// `name(arg, name := arg)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Call<'a> {
+    pub name: Cow<'a, str>,
+    pub args: Vec<(Option<Cow<'a, str>>, Expr<'a>)>,
+}
+
+/// `(a) union (b)`. operators are applied from left to right
+#[derive(Clone, Debug, PartialEq)]
+pub struct Set<'a> {
+    pub first: Expr<'a>,
+    pub rest: Vec<(SetOperator, Expr<'a>)>,
+}
+
+impl<'a> Expr<'a> {
+    /// with block of statement
+    pub fn withs(&self) -> Option<&[WithItem<'a>]> {
+        match self {
+            Expr::Select(x) => Some(&x.withs),
+            Expr::Insert(x) => Some(&x.withs),
+            Expr::Update(x) => Some(&x.withs),
+            Expr::Delete(x) => Some(&x.withs),
+            Expr::Group(x) => Some(&x.withs),
+            Expr::For(x) => Some(&x.withs),
+            _ => None,
+        }
+    }
+
+    /// with block of statement
+    pub fn withs_mut(&mut self) -> Option<&mut Vec<WithItem<'a>>> {
+        match self {
+            Expr::Select(x) => Some(&mut x.withs),
+            Expr::Insert(x) => Some(&mut x.withs),
+            Expr::Update(x) => Some(&mut x.withs),
+            Expr::Delete(x) => Some(&mut x.withs),
+            Expr::Group(x) => Some(&mut x.withs),
+            Expr::For(x) => Some(&mut x.withs),
+            _ => None,
+        }
+    }
+
+    /// calls `f` with each direct subexpression
+    pub fn visit_children_mut(&mut self, f: &mut dyn FnMut(&mut Expr<'a>)) {
+        match self {
+            Expr::Raw(_) | Expr::Literal(_) | Expr::Param(_) | Expr::Rendered(_) => {}
+            Expr::Paren(x) | Expr::Prefix(_, x) => f(x),
+            Expr::Select(x) => {
+                visit_withs_mut(&mut x.withs, f);
+                f(&mut x.subject);
+                visit_shape_mut(&mut x.shape, f);
+                visit_condition_mut(x.filter.as_mut(), f);
+                x.orders.iter_mut().for_each(|order| f(&mut order.by));
+            }
+            Expr::Insert(x) => {
+                visit_withs_mut(&mut x.withs, f);
+                x.assignments.iter_mut().for_each(|x| f(&mut x.value));
+
+                if let Some(expr) = x
+                    .unless_conflict
+                    .as_mut()
+                    .and_then(|x| x.else_expr.as_mut())
+                {
+                    f(expr);
+                }
+            }
+            Expr::Update(x) => {
+                visit_withs_mut(&mut x.withs, f);
+                f(&mut x.subject);
+                visit_condition_mut(x.filter.as_mut(), f);
+                x.assignments.iter_mut().for_each(|x| f(&mut x.value));
+            }
+            Expr::Delete(x) => {
+                visit_withs_mut(&mut x.withs, f);
+                visit_condition_mut(x.filter.as_mut(), f);
+                x.orders.iter_mut().for_each(|order| f(&mut order.by));
+            }
+            Expr::Group(x) => {
+                visit_withs_mut(&mut x.withs, f);
+            }
+            Expr::For(x) => {
+                visit_withs_mut(&mut x.withs, f);
+                f(&mut x.set);
+                f(&mut x.body);
+            }
+            Expr::Condition(x) => visit_condition_mut(Some(x), f),
+            Expr::IfElse(x) => {
+                for (cond, then) in &mut x.branches {
+                    visit_condition_mut(Some(cond), f);
+                    f(then);
+                }
+
+                if let Some(otherwise) = &mut x.otherwise {
+                    f(otherwise);
+                }
+            }
+            Expr::Coalesce(x) | Expr::Seq(x) => x.iter_mut().for_each(f),
+            Expr::Call(x) => x.args.iter_mut().for_each(|(_, x)| f(x)),
+            Expr::Set(x) => {
+                f(&mut x.first);
+                x.rest.iter_mut().for_each(|(_, x)| f(x));
+            }
+            Expr::ShapeElement(x) => visit_shape_mut(std::slice::from_mut(&mut **x), f),
+            Expr::WithItem(x) => visit_withs_mut(std::slice::from_mut(&mut **x), f),
+            Expr::UnlessConflict(x) => {
+                if let Some(expr) = &mut x.else_expr {
+                    f(expr);
+                }
+            }
+        }
+    }
+}
+
+impl Expr<'_> {
+    /// tree owning its text, e.g. to keep it after the builder is dropped
+    pub fn into_owned(self) -> Expr<'static> {
+        match self {
+            Expr::Raw(x) => Expr::Raw(owned(x)),
+            Expr::Literal(x) => Expr::Literal(owned(x)),
+            Expr::Param(x) => Expr::Param(Box::new(x.into_owned())),
+            Expr::Paren(x) => Expr::Paren(Box::new(x.into_owned())),
+            Expr::Select(x) => Expr::Select(Box::new(x.into_owned())),
+            Expr::Insert(x) => Expr::Insert(Box::new(x.into_owned())),
+            Expr::Update(x) => Expr::Update(Box::new(x.into_owned())),
+            Expr::Delete(x) => Expr::Delete(Box::new(x.into_owned())),
+            Expr::Group(x) => Expr::Group(Box::new(x.into_owned())),
+            Expr::For(x) => Expr::For(Box::new(x.into_owned())),
+            Expr::Condition(x) => Expr::Condition(Box::new(x.into_owned())),
+            Expr::IfElse(x) => Expr::IfElse(Box::new(x.into_owned())),
+            Expr::Coalesce(x) => Expr::Coalesce(x.into_iter().map(Expr::into_owned).collect()),
+            Expr::Prefix(op, x) => Expr::Prefix(owned(op), Box::new(x.into_owned())),
+            Expr::Call(x) => Expr::Call(Box::new(x.into_owned())),
+            Expr::Set(x) => Expr::Set(Box::new(x.into_owned())),
+            Expr::ShapeElement(x) => Expr::ShapeElement(Box::new(x.into_owned())),
+            Expr::WithItem(x) => Expr::WithItem(Box::new(x.into_owned())),
+            Expr::UnlessConflict(x) => Expr::UnlessConflict(Box::new(x.into_owned())),
+            Expr::Seq(x) => Expr::Seq(x.into_iter().map(Expr::into_owned).collect()),
+            Expr::Rendered(x) => Expr::Rendered(Box::new(x.into_owned())),
+        }
+    }
+}
+
+fn owned(x: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(x.into_owned())
+}
+
+fn owned_withs(withs: Vec<WithItem<'_>>) -> Vec<WithItem<'static>> {
+    withs.into_iter().map(WithItem::into_owned).collect()
+}
+
+fn owned_orders(orders: Vec<Order<'_>>) -> Vec<Order<'static>> {
+    orders
+        .into_iter()
+        .map(|x| Order {
+            by: x.by.into_owned(),
+            direction: x.direction,
+        })
+        .collect()
+}
+
+fn owned_assignments(assignments: Vec<Assignment<'_>>) -> Vec<Assignment<'static>> {
+    assignments
+        .into_iter()
+        .map(|x| Assignment {
+            name: owned(x.name),
+            assign: x.assign,
+            value: x.value.into_owned(),
+        })
+        .collect()
+}
+
+impl Param<'_> {
+    pub fn into_owned(self) -> Param<'static> {
+        Param {
+            name: owned(self.name),
+            ty: self.ty,
+            value: self.value,
+        }
+    }
+}
+
+impl Rendered<'_> {
+    pub fn into_owned(self) -> Rendered<'static> {
+        Rendered {
+            text: Cow::Owned(self.text.into_owned()),
+            slots: self
+                .slots
+                .into_iter()
+                .map(|x| x.map_left(owned).map_right(Param::into_owned))
+                .collect(),
+        }
+    }
+}
+
+impl WithItem<'_> {
+    pub fn into_owned(self) -> WithItem<'static> {
+        match self {
+            WithItem::Binding { name, value } => WithItem::Binding {
+                name: owned(name),
+                value: value.into_owned(),
+            },
+            WithItem::Module { alias, module } => WithItem::Module {
+                alias: alias.map(owned),
+                module: owned(module),
+            },
+        }
+    }
+}
+
+impl ShapeElement<'_> {
+    pub fn into_owned(self) -> ShapeElement<'static> {
+        match self {
+            ShapeElement::Splat => ShapeElement::Splat,
+            ShapeElement::DoubleSplat => ShapeElement::DoubleSplat,
+            ShapeElement::Field { name, shape } => ShapeElement::Field {
+                name: owned(name),
+                shape: shape.into_iter().map(ShapeElement::into_owned).collect(),
+            },
+            ShapeElement::Computed { name, expr } => ShapeElement::Computed {
+                name: owned(name),
+                expr: expr.into_owned(),
+            },
+        }
+    }
+}
+
+impl Select<'_> {
+    pub fn into_owned(self) -> Select<'static> {
+        Select {
+            withs: owned_withs(self.withs),
+            distinct: self.distinct,
+            subject: self.subject.into_owned(),
+            shape: self
+                .shape
+                .into_iter()
+                .map(ShapeElement::into_owned)
+                .collect(),
+            filter: self.filter.map(Condition::into_owned),
+            orders: owned_orders(self.orders),
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+impl Insert<'_> {
+    pub fn into_owned(self) -> Insert<'static> {
+        Insert {
+            withs: owned_withs(self.withs),
+            subject: owned(self.subject),
+            assignments: owned_assignments(self.assignments),
+            unless_conflict: self.unless_conflict.map(UnlessConflict::into_owned),
+        }
+    }
+}
+
+impl UnlessConflict<'_> {
+    pub fn into_owned(self) -> UnlessConflict<'static> {
+        UnlessConflict {
+            on: self.on.map(owned),
+            else_expr: self.else_expr.map(Expr::into_owned),
+        }
+    }
+}
+
+impl Update<'_> {
+    pub fn into_owned(self) -> Update<'static> {
+        Update {
+            withs: owned_withs(self.withs),
+            subject: self.subject.into_owned(),
+            filter: self.filter.map(Condition::into_owned),
+            assignments: owned_assignments(self.assignments),
+        }
+    }
+}
+
+impl Delete<'_> {
+    pub fn into_owned(self) -> Delete<'static> {
+        Delete {
+            withs: owned_withs(self.withs),
+            subject: owned(self.subject),
+            filter: self.filter.map(Condition::into_owned),
+            orders: owned_orders(self.orders),
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+impl Group<'_> {
+    pub fn into_owned(self) -> Group<'static> {
+        Group {
+            withs: owned_withs(self.withs),
+            subject: owned(self.subject),
+            shape: self.shape.map(owned),
+            using: self.using.into_iter().map(owned).collect(),
+            by: self.by.into_iter().map(owned).collect(),
+        }
+    }
+}
+
+impl For<'_> {
+    pub fn into_owned(self) -> For<'static> {
+        For {
+            withs: owned_withs(self.withs),
+            binding: owned(self.binding),
+            set: self.set.into_owned(),
+            body: self.body.into_owned(),
+        }
+    }
+}
+
+impl Condition<'_> {
+    pub fn into_owned(self) -> Condition<'static> {
+        let terms = self.terms.into_iter().map(|(and_or, term)| {
+            let term = match term {
+                Term::Raw(x) => Term::Raw(owned(x)),
+                Term::Arg { raw, arg } => Term::Arg {
+                    raw: owned(raw),
+                    arg: arg.into_owned(),
+                },
+                Term::Expr(x) => Term::Expr(x.into_owned()),
+                Term::Group(x) => Term::Group(x.into_owned()),
+            };
+
+            (and_or, term)
+        });
+
+        Condition {
+            not: self.not,
+            func: self.func.map(owned),
+            terms: terms.collect(),
+        }
+    }
+}
+
+impl IfElse<'_> {
+    pub fn into_owned(self) -> IfElse<'static> {
+        IfElse {
+            statement: self.statement,
+            branches: self
+                .branches
+                .into_iter()
+                .map(|(cond, then)| (cond.into_owned(), then.into_owned()))
+                .collect(),
+            otherwise: self.otherwise.map(Expr::into_owned),
+        }
+    }
+}
+
+impl Call<'_> {
+    pub fn into_owned(self) -> Call<'static> {
+        Call {
+            name: owned(self.name),
+            args: self
+                .args
+                .into_iter()
+                .map(|(name, arg)| (name.map(owned), arg.into_owned()))
+                .collect(),
+        }
+    }
+}
+
+impl Set<'_> {
+    pub fn into_owned(self) -> Set<'static> {
+        Set {
+            first: self.first.into_owned(),
+            rest: self
+                .rest
+                .into_iter()
+                .map(|(op, x)| (op, x.into_owned()))
+                .collect(),
+        }
+    }
+}
+
+fn visit_withs_mut<'a>(withs: &mut [WithItem<'a>], f: &mut dyn FnMut(&mut Expr<'a>)) {
+    for with in withs {
+        if let WithItem::Binding { value, .. } = with {
+            f(value);
+        }
+    }
+}
+
+fn visit_shape_mut<'a>(shape: &mut [ShapeElement<'a>], f: &mut dyn FnMut(&mut Expr<'a>)) {
+    for element in shape {
+        match element {
+            ShapeElement::Field { shape, .. } => visit_shape_mut(shape, f),
+            ShapeElement::Computed { expr, .. } => f(expr),
+            ShapeElement::Splat | ShapeElement::DoubleSplat => {}
+        }
+    }
+}
+
+fn visit_condition_mut<'a>(cond: Option<&mut Condition<'a>>, f: &mut dyn FnMut(&mut Expr<'a>)) {
+    let Some(cond) = cond else {
+        return;
+    };
+
+    for (_, term) in &mut cond.terms {
+        match term {
+            Term::Raw(_) => {}
+            Term::Arg { arg, .. } => f(arg),
+            Term::Expr(expr) => f(expr),
+            Term::Group(cond) => visit_condition_mut(Some(cond), f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn rewrite() {
+        let select = select("Book", crate::fields! { title });
+        let mut query = select.to_ast();
+
+        if let ast::Expr::Select(x) = &mut query {
+            x.limit = Some(10);
+        }
+
+        let query = query.to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  title,
+}
+limit 10"
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use either::Either;
+
+use super::*;
+
+/// `x if cond else y` or `if cond then x else y`
+///
+/// ```ignore
+/// if_else(filter().add(AND, ".price > $?", 100))
+///     .then("expensive")
+///     .else_if(filter().add(AND, ".price > $?", 10))
+///     .then("normal")
+///     .else_value("cheap");
+/// ```
+#[derive(Clone)]
+pub struct IfElse<'a> {
+    statement: bool,
+    branches: Vec<(Filter<'a>, Option<QueryArgOrExpr<'a>>)>,
+    otherwise: Option<QueryArgOrExpr<'a>>,
+}
+
+pub fn if_else<'a>(cond: Filter<'a>) -> IfElse<'a> {
+    IfElse::new(cond)
+}
+
+impl<'a> IfElse<'a> {
+    pub fn new(cond: Filter<'a>) -> Self {
+        Self {
+            statement: false,
+            branches: vec![(cond, None)],
+            otherwise: None,
+        }
+    }
+
+    /// render as `if cond then x else y`
+    pub fn statement(mut self) -> Self {
+        self.statement = true;
+
+        self
+    }
+
+    /// value of the last condition
+    pub fn then<T>(mut self, value: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        self.set_then(Either::Left(Arc::new(value)));
+
+        self
+    }
+
+    /// expression of the last condition
+    pub fn then_expr<T>(mut self, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.set_then(Either::Right(Arc::new(expr)));
+
+        self
+    }
+
+    pub fn else_if(mut self, cond: Filter<'a>) -> Self {
+        self.branches.push((cond, None));
+
+        self
+    }
+
+    pub fn else_value<T>(mut self, value: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        self.otherwise.replace(Either::Left(Arc::new(value)));
+
+        self
+    }
+
+    pub fn else_expr<T>(mut self, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.otherwise.replace(Either::Right(Arc::new(expr)));
+
+        self
+    }
+
+    fn set_then(&mut self, value: QueryArgOrExpr<'a>) {
+        if let Some((_, then)) = self.branches.last_mut() {
+            then.replace(value);
+        }
+    }
+}
+
+impl<'a> ToQuery for IfElse<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let branches = self.branches.iter().map(|(cond, then)| {
+            let then = match then {
+                Some(then) => arg_or_expr_ast(then),
+                // `BuildError::MissingThen` by `validate`, empty set by `to_query`
+                None => ast::Expr::Raw("{}".into()),
+            };
+
+            (cond.to_condition().unwrap_or_default(), then)
+        });
+
+        ast::Expr::IfElse(Box::new(ast::IfElse {
+            statement: self.statement,
+            branches: branches.collect(),
+            otherwise: self.otherwise.as_ref().map(arg_or_expr_ast),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        for (branch, (cond, then)) in self.branches.iter().enumerate() {
+            cond.validate()?;
+
+            if cond.to_condition().is_none() {
+                return Err(BuildError::EmptyIfCondition { branch });
+            }
+
+            let Some(then) = then else {
+                return Err(BuildError::MissingThen { branch });
+            };
+
+            validate_arg_or_expr(then)?;
+        }
+
+        self.otherwise.iter().try_for_each(validate_arg_or_expr)
+    }
+}
+
+/// `a ?? b ?? c`
+#[derive(Clone)]
+pub struct Coalesce<'a> {
+    operands: Vec<QueryArgOrExpr<'a>>,
+}
+
+pub fn coalesce<'a, T>(value: T) -> Coalesce<'a>
+where
+    T: ToQueryArg + 'a,
+{
+    Coalesce::new().or(value)
+}
+
+pub fn coalesce_expr<'a, T>(expr: T) -> Coalesce<'a>
+where
+    T: ToQuery + 'a,
+{
+    Coalesce::new().or_expr(expr)
+}
+
+impl<'a> Coalesce<'a> {
+    pub fn new() -> Self {
+        Self {
+            operands: Vec::new(),
+        }
+    }
+
+    pub fn or<T>(mut self, value: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        self.operands.push(Either::Left(Arc::new(value)));
+
+        self
+    }
+
+    pub fn or_expr<T>(mut self, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.operands.push(Either::Right(Arc::new(expr)));
+
+        self
+    }
+}
+
+impl<'a> Default for Coalesce<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ToQuery for Coalesce<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Coalesce(self.operands.iter().map(arg_or_expr_ast).collect())
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        if self.operands.is_empty() {
+            return Err(BuildError::EmptyCoalesce);
+        }
+
+        self.operands.iter().try_for_each(validate_arg_or_expr)
+    }
+}
+
+/// `exists (expr)`
+#[derive(Clone)]
+pub struct Exists<'a> {
+    expr: Arc<dyn ToQuery + 'a>,
+}
+
+pub fn exists<'a, T>(expr: T) -> Exists<'a>
+where
+    T: ToQuery + 'a,
+{
+    Exists::new(expr)
+}
+
+impl<'a> Exists<'a> {
+    pub fn new<T>(expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self {
+            expr: Arc::new(expr),
+        }
+    }
+}
+
+impl<'a> ToQuery for Exists<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Prefix("exists".into(), Box::new(self.expr.to_ast()))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.expr.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let price = if_else(filter().add(AND, ".price > $?", 100))
+            .then("expensive")
+            .else_if(filter().add(AND, ".price > $?", 10))
+            .then("normal")
+            .else_value("cheap");
+
+        let fields = crate::fields! {
+            title,
+            price_kind := price.clone(),
+            author_name := coalesce_expr(raw(".author.name")).or("unknown"),
+        };
+
+        let query = select("Book", fields)
+            .filter(filter().add_expr(AND, exists(raw(".author"))))
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  title,
+  price_kind := (
+    <str>'expensive' if (
+      .price > 100
+    ) else <str>'normal' if (
+      .price > 10
+    ) else <str>'cheap'
+  ),
+  author_name := (
+    (
+      .author.name
+    ) ?? <str>'unknown'
+  ),
+}
+filter
+  exists (
+    .author
+  )"
+        );
+
+        let query = if_else(filter().add(AND, "exists $?", raw("book")))
+            .then_expr(
+                update("Book")
+                    .filter(filter().add(AND, ".uid = $?", 1))
+                    .set("released", Assign::Replace, true),
+            )
+            .else_expr(insert("Book").set("uid", 1))
+            .statement()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+if (
+  exists book
+) then (
+  update Book
+  filter
+    .uid = 1
+  set {
+    released := true,
+  }
+) else (
+  insert Book {
+    uid := 1,
+  }
+)"
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let cond = || filter().add(AND, ".price > $?", 100);
+
+        assert_eq!(
+            if_else(filter()).then(1).try_to_query(),
+            Err(BuildError::EmptyIfCondition { branch: 0 })
+        );
+        assert_eq!(
+            if_else(cond()).then(1).else_if(cond()).try_to_query(),
+            Err(BuildError::MissingThen { branch: 1 })
+        );
+        assert_eq!(
+            Coalesce::new().try_to_query(),
+            Err(BuildError::EmptyCoalesce)
+        );
+        assert_eq!(
+            if_else(cond()).then(1).try_to_query().unwrap(),
+            "\
+1 if (
+  .price > 100
+) else {}"
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::borrow::Cow;
+
+use super::*;
+
+#[derive(Clone)]
+pub struct DeleteBuilder<'a> {
+    target: Cow<'a, str>,
+    withs: Vec<With<'a>>,
+    filter: Option<Filter<'a>>,
+    all: bool,
+    soft_delete: Option<SoftDelete<'a>>,
+    with_deleted: bool,
+    skip: Option<usize>,
+    take: Option<usize>,
+    orders: Vec<OrderBy<'a>>,
+    /// select (delete ...) { fields }
+    fields: Vec<Field<'a>>,
+}
+
+pub fn delete<'a>(target: impl Into<Cow<'a, str>>) -> DeleteBuilder<'a> {
+    DeleteBuilder::new(target)
+}
+
+/// target is `T::type_name()`. with `T::soft_delete()` objects are soft-deleted,
+/// rendered as `update ... set { property := value }`
+pub fn delete_as<'a, T>() -> DeleteBuilder<'a>
+where
+    T: TypeName,
+{
+    let mut delete = DeleteBuilder::new(T::type_name());
+
+    delete.soft_delete = T::soft_delete();
+
+    delete
+}
+
+impl<'a> DeleteBuilder<'a> {
+    pub fn new(target: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            target: target.into(),
+            withs: Vec::new(),
+            filter: None,
+            all: false,
+            soft_delete: None,
+            with_deleted: false,
+            skip: None,
+            take: None,
+            orders: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, with: With<'a>) -> Self {
+        self.withs.push(with);
+
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter<'a>) -> Self {
+        self.filter.replace(filter);
+
+        self
+    }
+
+    /// ignore soft-delete policy and delete objects
+    pub fn with_deleted(mut self) -> Self {
+        self.with_deleted = true;
+
+        self
+    }
+
+    /// allow delete without filter. otherwise `try_to_query` refuses it
+    /// and `to_query` renders `filter false`
+    pub fn all(mut self) -> Self {
+        self.all = true;
+
+        self
+    }
+
+    pub fn skip(mut self, n: usize) -> Self {
+        self.skip.replace(n);
+
+        self
+    }
+
+    pub fn take(mut self, n: usize) -> Self {
+        self.take.replace(n);
+
+        self
+    }
+
+    pub fn order_by(mut self, ord: OrderBy<'a>) -> Self {
+        self.orders.push(ord);
+
+        self
+    }
+
+    /// shape of deleted objects
+    pub fn fields<T>(mut self, fields: T) -> Self
+    where
+        T: IntoIterator<Item = Field<'a>>,
+    {
+        self.fields.extend(fields);
+
+        self
+    }
+
+    fn policy(&self) -> Option<&SoftDelete<'a>> {
+        self.soft_delete.as_ref().filter(|_| !self.with_deleted)
+    }
+
+    fn orders(&self) -> Vec<ast::Order<'_>> {
+        self.orders.iter().map(OrderBy::to_order).collect()
+    }
+
+    fn offset(&self) -> Option<usize> {
+        self.skip.filter(|n| *n > 0)
+    }
+
+    fn filtered(&self) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|filter| !filter.is_empty())
+    }
+
+    /// `delete`, or `update` with soft-delete policy
+    fn statement(&self) -> ast::Expr<'_> {
+        let refused = (!self.filtered() && !self.all).then(refused_filter);
+
+        let Some(policy) = self.policy() else {
+            return ast::Expr::Delete(Box::new(ast::Delete {
+                withs: Vec::new(),
+                subject: self.target.as_ref().into(),
+                filter: refused.or_else(|| self.filter.as_ref().and_then(Filter::to_condition)),
+                orders: self.orders(),
+                offset: self.offset(),
+                limit: self.take,
+            }));
+        };
+
+        let filter = Some(refused.unwrap_or_else(|| policy.condition(self.filter.as_ref())));
+
+        let (subject, filter) =
+            if self.orders.is_empty() && self.skip.is_none() && self.take.is_none() {
+                (ast::Expr::Raw(self.target.as_ref().into()), filter)
+            } else {
+                let select = ast::Select {
+                    filter,
+                    orders: self.orders(),
+                    offset: self.offset(),
+                    limit: self.take,
+                    ..ast::Select::new(ast::Expr::Raw(self.target.as_ref().into()))
+                };
+
+                (
+                    ast::Expr::Paren(Box::new(ast::Expr::Select(Box::new(select)))),
+                    None,
+                )
+            };
+
+        ast::Expr::Update(Box::new(ast::Update {
+            withs: Vec::new(),
+            subject,
+            filter,
+            assignments: vec![policy.assignment()],
+        }))
+    }
+}
+
+impl<'a> ToQuery for DeleteBuilder<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let mut statement = self.statement();
+
+        // returning
+        if !self.fields.is_empty() {
+            let select = ast::Select {
+                shape: self.fields.iter().map(Field::to_shape_element).collect(),
+                ..ast::Select::new(ast::Expr::Paren(Box::new(statement)))
+            };
+
+            statement = ast::Expr::Select(Box::new(select));
+        }
+
+        if let Some(withs) = statement.withs_mut() {
+            *withs = self.withs.iter().map(With::to_with_item).collect();
+        }
+
+        statement
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        check_withs(&self.withs)?;
+        self.filter.iter().try_for_each(Filter::validate)?;
+        self.fields.iter().try_for_each(Field::validate)?;
+
+        if !self.filtered() && !self.all {
+            return Err(BuildError::Unfiltered {
+                statement: "delete",
+                target: self.target.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let query = delete("Book")
+            .filter(filter().add(AND, ".uid = $?", 1234))
+            .order_by(order_by(".uid", DESC))
+            .take(1)
+            .fields(crate::fields! { uid, title })
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select (
+  delete Book
+  filter
+    .uid = 1234
+  order by
+    .uid desc
+  limit 1
+) {
+  uid,
+  title,
+}"
+        );
+    }
+
+    #[test]
+    fn print_soft_delete() {
+        struct Book;
+
+        impl TypeName for Book {
+            fn type_name() -> &'static str {
+                "Book"
+            }
+
+            fn soft_delete() -> Option<SoftDelete<'static>> {
+                Some(soft_delete("deleted_at"))
+            }
+        }
+
+        let uid = || filter().add(AND, ".uid = $?", 1234);
+
+        let query = delete_as::<Book>()
+            .filter(uid().add(OR, ".uid = $?", 5678))
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+filter
+  not exists .deleted_at and
+  (
+    .uid = 1234 or
+    .uid = 5678
+  )
+set {
+  deleted_at := datetime_current(),
+}"
+        );
+
+        let query = delete_as::<Book>()
+            .filter(uid())
+            .take(1)
+            .fields(crate::fields! { uid })
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select (
+  update (
+    select Book
+    filter
+      not exists .deleted_at and
+      (
+        .uid = 1234
+      )
+    limit 1
+  )
+  set {
+    deleted_at := datetime_current(),
+  }
+) {
+  uid,
+}"
+        );
+
+        assert_eq!(
+            delete_as::<Book>().to_query(),
+            "\
+update Book
+filter
+  false
+set {
+  deleted_at := datetime_current(),
+}"
+        );
+
+        let query = delete_as::<Book>().filter(uid()).with_deleted().to_query();
+
+        assert_eq!(query, "delete Book\nfilter\n  .uid = 1234");
+
+        let query = select_as::<Book>(crate::fields! { uid })
+            .filter(uid())
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  uid,
+}
+filter
+  not exists .deleted_at and
+  (
+    .uid = 1234
+  )"
+        );
+
+        let query = select_as::<Book>(crate::fields! { uid })
+            .with_deleted()
+            .to_query();
+
+        assert_eq!(query, "select Book {\n  uid,\n}");
+
+        let query = update_as::<Book>()
+            .filter(uid())
+            .set("title", Assign::Replace, "a")
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+filter
+  not exists .deleted_at and
+  (
+    .uid = 1234
+  )
+set {
+  title := <str>'a',
+}"
+        );
+    }
+}
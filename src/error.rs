@@ -0,0 +1,302 @@
+use std::fmt;
+
+use edgedb_protocol::value::Value;
+
+/// invalid builder state found before rendering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// `update`/`delete` without filter. call `.all()` if intended
+    Unfiltered {
+        statement: &'static str,
+        target: String,
+    },
+    /// same name bound twice, or a second `module` declaration, in one with block
+    DuplicateWith { name: String },
+    /// `With::new(name)` without `.value(..)` or `.expr(..)`
+    UnsetWith { name: String },
+    /// `update` without `set`
+    NoValues { target: String },
+    /// `group` without `by`
+    NoGroupBy { target: String },
+    /// filter condition with unmatched `(` or `)`
+    UnbalancedParens { condition: String },
+    /// `if_else` branch whose filter is empty. `branch` counts from 0
+    EmptyIfCondition { branch: usize },
+    /// `if_else` branch without `then`
+    MissingThen { branch: usize },
+    /// `Coalesce::new()` without operands
+    EmptyCoalesce,
+    /// struct payload which cannot be written as EdgeQL
+    #[cfg(feature = "serde")]
+    InvalidPayload { message: String },
+    /// merge patch field which is unknown or not allowed. e.g. `author.email`
+    #[cfg(feature = "serde")]
+    ForbiddenField { path: String },
+    /// template slot without value
+    UnboundSlot { name: String },
+    /// value bound to a name the template does not have
+    UnknownSlot { name: String },
+    /// slot bound to different values in one query. e.g. two templates nested in a query
+    ConflictingSlot { name: String },
+    /// slot in a query which is not given to `Template::new`
+    StraySlot { name: String },
+    /// value bound to a slot which has no argument type. e.g. `raw(..)` or an empty `Vec`
+    NotArgument { name: String },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Unfiltered { statement, target } => write!(
+                f,
+                "`{statement} {target}` has no filter; call `.all()` to affect every object"
+            ),
+            BuildError::DuplicateWith { name } => write!(f, "`{name}` is bound twice in with"),
+            BuildError::UnsetWith { name } => write!(f, "`{name}` in with has no value"),
+            BuildError::NoValues { target } => write!(f, "`update {target}` sets nothing"),
+            BuildError::NoGroupBy { target } => write!(f, "`group {target}` has no `by`"),
+            BuildError::UnbalancedParens { condition } => {
+                write!(f, "unbalanced parentheses in `{condition}`")
+            }
+            BuildError::EmptyIfCondition { branch } => {
+                write!(f, "condition of if branch {branch} is empty")
+            }
+            BuildError::MissingThen { branch } => write!(f, "if branch {branch} has no `then`"),
+            BuildError::EmptyCoalesce => write!(f, "coalesce has no operands"),
+            #[cfg(feature = "serde")]
+            BuildError::InvalidPayload { message } => write!(f, "invalid payload: {message}"),
+            #[cfg(feature = "serde")]
+            BuildError::ForbiddenField { path } => write!(f, "`{path}` cannot be patched"),
+            BuildError::UnboundSlot { name } => write!(f, "slot `{name}` has no value"),
+            BuildError::UnknownSlot { name } => write!(f, "template has no slot `{name}`"),
+            BuildError::ConflictingSlot { name } => {
+                write!(f, "slot `{name}` is bound to different values")
+            }
+            BuildError::StraySlot { name } => write!(f, "slot `{name}` is outside of a template"),
+            BuildError::NotArgument { name } => {
+                write!(f, "value of slot `{name}` cannot be a query argument")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// client error with the query which caused it
+///
+/// `Display` and `Debug` show the query with arguments replaced by `$?`.
+/// the query with values is only given by `query()`, and `$name` arguments by `args()`
+pub struct QueryError {
+    source: edgedb_tokio::Error,
+    query: String,
+    redacted: String,
+    args: Option<Value>,
+}
+
+impl QueryError {
+    pub(crate) fn new(
+        source: edgedb_tokio::Error,
+        query: String,
+        redacted: String,
+        args: Option<Value>,
+    ) -> Self {
+        Self {
+            source,
+            query,
+            redacted,
+            args,
+        }
+    }
+
+    /// rendered query as sent, with argument values
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// rendered query with arguments replaced by `$?`
+    pub fn redacted_query(&self) -> &str {
+        &self.redacted
+    }
+
+    /// values of `$name` parameters as sent. `None` if the query has none
+    pub fn args(&self) -> Option<&Value> {
+        self.args.as_ref()
+    }
+
+    pub fn into_inner(self) -> edgedb_tokio::Error {
+        self.source
+    }
+}
+
+impl fmt::Debug for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueryError")
+            .field("source", &self.source)
+            .field("query", &self.redacted)
+            .finish()
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\nquery:\n{}", self.source, self.redacted)
+    }
+}
+
+impl std::error::Error for QueryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// JSON result which does not fit the requested type
+///
+/// like `QueryError`, `Display` and `Debug` show the redacted query
+#[cfg(feature = "serde")]
+pub struct JsonError {
+    source: serde_path_to_error::Error<serde_json::Error>,
+    query: String,
+    redacted: String,
+    args: Option<Value>,
+}
+
+#[cfg(feature = "serde")]
+impl JsonError {
+    pub(crate) fn new(
+        source: serde_path_to_error::Error<serde_json::Error>,
+        query: String,
+        redacted: String,
+        args: Option<Value>,
+    ) -> Self {
+        Self {
+            source,
+            query,
+            redacted,
+            args,
+        }
+    }
+
+    /// path to the failed value. e.g. `[0].author.name`
+    pub fn path(&self) -> String {
+        self.source.path().to_string()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn redacted_query(&self) -> &str {
+        &self.redacted
+    }
+
+    pub fn args(&self) -> Option<&Value> {
+        self.args.as_ref()
+    }
+
+    pub fn into_inner(self) -> serde_json::Error {
+        self.source.into_inner()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Debug for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonError")
+            .field("path", &self.path())
+            .field("source", self.source.inner())
+            .field("query", &self.redacted)
+            .finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "at `{}`: {}\nquery:\n{}",
+            self.source.path(),
+            self.source.inner(),
+            self.redacted
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.inner())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Build(BuildError),
+    /// `query_required_single` returned nothing
+    NoData(QueryError),
+    /// `assert_single`, `assert_exists` or result cardinality violated
+    Cardinality(QueryError),
+    Query(QueryError),
+    /// `query_as_json` result does not fit the type
+    #[cfg(feature = "serde")]
+    Decode(JsonError),
+}
+
+impl Error {
+    /// classifies client error by its kind
+    pub(crate) fn execution(err: QueryError) -> Self {
+        use edgedb_tokio::errors::{
+            CardinalityViolationError, NoDataError, ResultCardinalityMismatchError,
+        };
+
+        if err.source.is::<NoDataError>() {
+            Self::NoData(err)
+        } else if err.source.is::<CardinalityViolationError>()
+            || err.source.is::<ResultCardinalityMismatchError>()
+        {
+            Self::Cardinality(err)
+        } else {
+            Self::Query(err)
+        }
+    }
+
+    /// `None` if failed before execution
+    pub fn query_error(&self) -> Option<&QueryError> {
+        match self {
+            Error::Build(_) => None,
+            #[cfg(feature = "serde")]
+            Error::Decode(_) => None,
+            Error::NoData(err) | Error::Cardinality(err) | Error::Query(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Build(err) => write!(f, "invalid query: {err}"),
+            Error::NoData(err) => write!(f, "no data: {err}"),
+            Error::Cardinality(err) => write!(f, "cardinality violation: {err}"),
+            Error::Query(err) => write!(f, "query failed: {err}"),
+            #[cfg(feature = "serde")]
+            Error::Decode(err) => write!(f, "invalid result: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Build(err) => Some(err),
+            Error::NoData(err) | Error::Cardinality(err) | Error::Query(err) => Some(err),
+            #[cfg(feature = "serde")]
+            Error::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(err: BuildError) -> Self {
+        Self::Build(err)
+    }
+}
@@ -1,24 +1,55 @@
-use super::{push_str, ToQuery};
+use std::{borrow::Cow, sync::Arc};
+
+use super::{ast, BuildError, ToQuery};
 
 #[derive(Clone)]
 pub enum FieldType<'a> {
-    Expr(Box<dyn ToQuery + 'a>),
+    Expr(Arc<dyn ToQuery + 'a>),
     Field(Vec<Field<'a>>),
     SingleSplat,
     DoubleSplat,
 }
 
+/// name which may be absent. `None`, borrowed or owned
+pub trait OptionalName<'a> {
+    fn into_name(self) -> Option<Cow<'a, str>>;
+}
+
+impl<'a> OptionalName<'a> for Option<&'a str> {
+    fn into_name(self) -> Option<Cow<'a, str>> {
+        self.map(Cow::Borrowed)
+    }
+}
+
+impl<'a> OptionalName<'a> for &'a str {
+    fn into_name(self) -> Option<Cow<'a, str>> {
+        Some(Cow::Borrowed(self))
+    }
+}
+
+impl<'a> OptionalName<'a> for String {
+    fn into_name(self) -> Option<Cow<'a, str>> {
+        Some(Cow::Owned(self))
+    }
+}
+
+impl<'a> OptionalName<'a> for Cow<'a, str> {
+    fn into_name(self) -> Option<Cow<'a, str>> {
+        Some(self)
+    }
+}
+
 #[derive(Clone)]
 pub struct Field<'a> {
     /// None if splat
-    name: Option<&'a str>,
+    name: Option<Cow<'a, str>>,
     fields: FieldType<'a>,
 }
 
 impl<'a> Field<'a> {
-    pub fn new(name: impl Into<Option<&'a str>>) -> Self {
+    pub fn new(name: impl OptionalName<'a>) -> Self {
         Self {
-            name: name.into(),
+            name: name.into_name(),
             fields: FieldType::Field(Vec::new()),
         }
     }
@@ -40,8 +71,8 @@ impl<'a> Field<'a> {
         T: ToQuery + 'a,
     {
         match &mut self.fields {
-            FieldType::Expr(x) => *x = Box::new(expr),
-            _ => self.fields = FieldType::Expr(Box::new(expr)),
+            FieldType::Expr(x) => *x = Arc::new(expr),
+            _ => self.fields = FieldType::Expr(Arc::new(expr)),
         }
 
         self
@@ -72,66 +103,36 @@ impl<'a> Field<'a> {
     }
 }
 
-impl<'a> ToQuery for Field<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        if let Some(name) = self.name {
-            push_str(q, name, indent);
-        }
+impl<'a> Field<'a> {
+    pub(crate) fn to_shape_element(&self) -> ast::ShapeElement<'_> {
+        let name = Cow::Borrowed(self.name.as_deref().unwrap_or_default());
 
         match &self.fields {
-            FieldType::SingleSplat => {
-                q.push('*');
-                q.push(',');
-            }
-            FieldType::DoubleSplat => {
-                q.push_str("**");
-                q.push(',');
-            }
-            FieldType::Expr(expr) => {
-                q.push(' ');
-                q.push_str(":=");
-                q.push(' ');
-
-                q.push('(');
-                q.push('\n');
-
-                q.push_str(&expr.to_query_with_indent(2 + indent));
-
-                q.push('\n');
-                push_str(q, ")", indent);
-            }
-            FieldType::Field(nested_fields) if !nested_fields.is_empty() => {
-                q.push(':');
-                q.push(' ');
-
-                q.push('{');
-                q.push('\n');
-
-                let mut nested_fields = nested_fields.iter();
-
-                if let Some(nested) = nested_fields.next() {
-                    q.push_str(&nested.to_query_with_indent(2 + indent));
-                }
-
-                for nested in nested_fields {
-                    q.push('\n');
-                    q.push_str(&nested.to_query_with_indent(2 + indent));
-                }
+            FieldType::SingleSplat => ast::ShapeElement::Splat,
+            FieldType::DoubleSplat => ast::ShapeElement::DoubleSplat,
+            FieldType::Expr(expr) => ast::ShapeElement::Computed {
+                name,
+                expr: expr.to_ast(),
+            },
+            FieldType::Field(fields) => ast::ShapeElement::Field {
+                name,
+                shape: fields.iter().map(Field::to_shape_element).collect(),
+            },
+        }
+    }
+}
 
-                q.push('\n');
-                push_str(q, "}", indent);
-                q.push(',');
-            }
+impl<'a> ToQuery for Field<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::ShapeElement(Box::new(self.to_shape_element()))
+    }
 
-            _ => {
-                q.push(',');
-            }
+    fn validate(&self) -> Result<(), BuildError> {
+        match &self.fields {
+            FieldType::Expr(expr) => expr.validate(),
+            FieldType::Field(fields) => fields.iter().try_for_each(Field::validate),
+            FieldType::SingleSplat | FieldType::DoubleSplat => Ok(()),
         }
-
-        qx
     }
 }
 
@@ -267,7 +268,7 @@ macro_rules! fields {
 
 #[cfg(test)]
 mod tests {
-    use crate::{push_fields, raw};
+    use crate::{path, push_fields, raw, select, SelectBuilder, ToQuery};
 
     use super::*;
 
@@ -345,4 +346,41 @@ mod tests {
 
         println!("{r}");
     }
+
+    #[test]
+    fn owned() {
+        fn build(target: String, fields: Vec<String>) -> SelectBuilder<'static> {
+            let author = path(None)
+                .prop(String::from("author"))
+                .prop(String::from("name"));
+
+            let author_name = Field::new(String::from("author_name")).expr(author);
+
+            select(
+                target,
+                fields.into_iter().map(Field::new).chain([author_name]),
+            )
+        }
+
+        let query = build(
+            "Book".to_string(),
+            vec!["uid".to_string(), "title".to_string()],
+        );
+
+        let handle = std::thread::spawn(move || query.to_query());
+
+        let query = handle.join().unwrap();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  uid,
+  title,
+  author_name := (
+    .author.name
+  ),
+}"
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use either::Either;
+use std::borrow::Cow;
 
 use super::*;
 
@@ -9,7 +9,7 @@ pub enum Not {
 
 pub const NOT: Not = Not::Not;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AndOr {
     And,
     Or,
@@ -31,13 +31,18 @@ impl AndOr {
 
 // }
 
-type Inner<'a> = (&'a str, Box<dyn ToQueryArg + 'a>);
+#[derive(Clone)]
+enum Condition<'a> {
+    Arg(Cow<'a, str>, Arc<dyn ToQueryArg + 'a>),
+    Expr(Arc<dyn ToQuery + 'a>),
+    Filter(Filter<'a>),
+}
 
 #[derive(Clone)]
 pub struct Filter<'a> {
     not: Option<Not>,
-    func: Option<&'a str>,
-    qs: Vec<(AndOr, Either<Inner<'a>, Filter<'a>>)>,
+    func: Option<Cow<'a, str>>,
+    qs: Vec<(AndOr, Condition<'a>)>,
 }
 
 pub fn filter<'a>() -> Filter<'a> {
@@ -63,19 +68,30 @@ impl<'a> Filter<'a> {
         }
     }
 
-    pub fn func(mut self, func_name: &'a str) -> Self {
-        self.func.replace(func_name);
+    pub fn func(mut self, func_name: impl Into<Cow<'a, str>>) -> Self {
+        self.func.replace(func_name.into());
 
         self
     }
 
-    pub fn add(mut self, and_or: AndOr, q: &'a str, arg: impl ToQueryArg + 'a) -> Self {
-        self.qs.push((and_or, (Either::Left((q, Box::new(arg))))));
+    pub fn add(
+        mut self,
+        and_or: AndOr,
+        q: impl Into<Cow<'a, str>>,
+        arg: impl ToQueryArg + 'a,
+    ) -> Self {
+        self.qs
+            .push((and_or, Condition::Arg(q.into(), Arc::new(arg))));
 
         self
     }
 
-    pub fn add_opt(self, and_or: AndOr, q: &'a str, arg: Option<impl ToQueryArg + 'a>) -> Self {
+    pub fn add_opt(
+        self,
+        and_or: AndOr,
+        q: impl Into<Cow<'a, str>>,
+        arg: Option<impl ToQueryArg + 'a>,
+    ) -> Self {
         if let Some(arg) = arg {
             self.add(and_or, q, arg)
         } else {
@@ -83,91 +99,113 @@ impl<'a> Filter<'a> {
         }
     }
 
+    /// expression with boolean result. e.g. `exists(...)`
+    pub fn add_expr<T>(mut self, and_or: AndOr, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.qs.push((and_or, Condition::Expr(Arc::new(expr))));
+
+        self
+    }
+
     pub fn add_filter(mut self, and_or: AndOr, filter: Filter<'a>) -> Self {
-        self.qs.push((and_or, Either::Right(filter)));
+        self.qs.push((and_or, Condition::Filter(filter)));
 
         self
     }
 
     pub fn is_empty(&self) -> bool {
-        self.qs.is_empty()
+        self.qs
+            .iter()
+            .all(|(_, x)| matches!(x, Condition::Filter(x) if x.is_empty()))
     }
 
-    fn to_query_internal(&self, indent: usize, wrapped_by_parentheses: bool) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        let is_not = matches!(self.not, Some(Not::Not));
-        let has_func = self.func.is_some();
-
-        if is_not {
-            push_str(q, "not", indent);
-            q.push(' ');
-        }
-
-        if has_func {
-            q.push_str(self.func.unwrap());
+    /// `None` if empty
+    pub(crate) fn to_condition(&self) -> Option<ast::Condition<'_>> {
+        if self.is_empty() {
+            return None;
         }
 
-        if is_not || has_func {
-            q.push('(');
-            q.push('\n');
-        }
+        let terms = self.qs.iter().filter_map(|(and_or, x)| {
+            let term = match x {
+                Condition::Arg(x, arg) => ast::Term::Arg {
+                    raw: x.as_ref().into(),
+                    arg: ast::Expr::Literal(arg.to_query_arg().into()),
+                },
+                Condition::Expr(expr) => ast::Term::Expr(expr.to_ast()),
+                // empty nested filters would render as `()`
+                Condition::Filter(x) => ast::Term::Group(x.to_condition()?),
+            };
+
+            Some((*and_or, term))
+        });
+
+        Some(ast::Condition {
+            not: self.not.is_some(),
+            func: self.func.as_deref().map(Cow::Borrowed),
+            terms: terms.collect(),
+        })
+    }
+}
 
-        let mut qs = self.qs.iter();
+/// `filter false` in place of a missing filter of update or delete without `.all()`.
+/// `validate` refuses them, this keeps `to_query` from rendering a mutation of every object
+pub(crate) fn refused_filter<'q>() -> ast::Condition<'q> {
+    ast::Condition {
+        not: false,
+        func: None,
+        terms: vec![(AND, ast::Term::Raw("false".into()))],
+    }
+}
 
-        if let Some((_, x)) = qs.next() {
-            match x {
-                Either::Left((x, arg)) => {
-                    if wrapped_by_parentheses {
-                        push(q, '(', 2 + indent);
-                    } else {
-                        push_str(q, "", 2 + indent);
-                    }
-
-                    q.push_str(&x.replacen(ARG_IDENTITY, &arg.to_query_arg(), 1));
-                }
-                Either::Right(x) => {
-                    //
-                    q.push_str(&x.to_query_internal(indent, true));
-                }
-            }
+/// `BuildError::UnbalancedParens` if parentheses outside of string literals do not match
+fn check_parens(condition: &str) -> Result<(), BuildError> {
+    let unbalanced = || BuildError::UnbalancedParens {
+        condition: condition.to_string(),
+    };
+
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in condition.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth = depth.checked_sub(1).ok_or_else(unbalanced)?,
+                _ => {}
+            },
         }
+    }
 
-        for (and_or, x) in qs {
-            q.push(' ');
-            q.push_str(and_or.as_str());
+    if depth > 0 {
+        return Err(unbalanced());
+    }
 
-            q.push('\n');
+    Ok(())
+}
 
+impl<'a> ToQuery for Filter<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Condition(Box::new(self.to_condition().unwrap_or_default()))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        for (_, x) in &self.qs {
             match x {
-                Either::Left((x, arg)) => {
-                    let x = x.replacen(ARG_IDENTITY, &arg.to_query_arg(), 1);
-                    push_str(q, &x, 2 + indent);
-
-                    if wrapped_by_parentheses {
-                        q.push(')');
-                    }
-                }
-                Either::Right(x) => {
-                    //
-                    q.push_str(&x.to_query_internal(indent, true));
-                }
+                Condition::Arg(x, _) => check_parens(x)?,
+                Condition::Expr(expr) => expr.validate()?,
+                Condition::Filter(x) => x.validate()?,
             }
         }
 
-        if is_not || has_func {
-            q.push('\n');
-            push(q, ')', indent);
-        }
-
-        qx
-    }
-}
-
-impl<'a> ToQuery for Filter<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        self.to_query_internal(indent, false)
+        Ok(())
     }
 }
 
@@ -191,4 +229,79 @@ mod tests {
 
         println!("{r}");
     }
+
+    #[test]
+    fn shared() {
+        let tags = select("BookTag", crate::fields! { name }).filter(filter().add(
+            AND,
+            ".kind = $?",
+            "arg",
+        ));
+        let by_tag = filter().add_expr(AND, exists(tags));
+
+        let books = select("Book", crate::fields! { title }).filter(by_tag.clone());
+        let count = func("count").arg_expr(select("Book", []).filter(by_tag));
+
+        assert_eq!(books.to_query(), books.clone().to_query());
+
+        let books = books.to_string();
+        let count = count.to_string();
+
+        assert_eq!(
+            books,
+            "\
+select Book {
+  title,
+}
+filter
+  exists (
+    select BookTag {
+      name,
+    }
+    filter
+      .kind = <str>'arg'
+  )"
+        );
+        assert_eq!(
+            count,
+            "\
+count(
+  (
+    select Book
+    filter
+      exists (
+        select BookTag {
+          name,
+        }
+        filter
+          .kind = <str>'arg'
+      )
+  )
+)"
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let ok = filter().add(AND, "(.title = $?) and .note = ')'", "a (b");
+        let unbalanced = filter().add(AND, "(.uid = $?", 1234);
+
+        assert_eq!(ok.validate(), Ok(()));
+        assert_eq!(
+            unbalanced.validate(),
+            Err(BuildError::UnbalancedParens {
+                condition: "(.uid = $?".to_string()
+            })
+        );
+
+        // nested builders are validated too
+        let query = update("Book")
+            .filter(filter().add_expr(AND, exists(select("Author", []).filter(unbalanced))))
+            .set("released", Assign::Replace, true);
+
+        assert!(matches!(
+            query.try_to_query(),
+            Err(BuildError::UnbalancedParens { .. })
+        ));
+    }
 }
@@ -1,74 +1,261 @@
-use super::{push_str, push_withs, ToQuery, With};
+use std::{borrow::Cow, fmt};
+
+use either::Either;
+
+use super::*;
+
+/// state of `ForInBuilder` before the body is set
+#[derive(Clone)]
+pub struct NoBody;
 
 #[derive(Clone)]
-pub struct ForInBuilder<'a> {
-    pub arr: &'a str,
-    pub elem: &'a str,
+pub struct Body<'a>(Arc<dyn ToQuery + 'a>);
+
+/// `for x in set union (body)`
+///
+/// ```ignore
+/// forin(vec![(1, "a"), (2, "b")], |(uid, title): (ForElem, ForElem)| {
+///     insert("Book").set("uid", uid).set("title", title)
+/// });
+/// ```
+#[derive(Clone)]
+pub struct ForInBuilder<'a, B = Body<'a>> {
+    elem: Cow<'a, str>,
+    set: QueryArgOrExpr<'a>,
     withs: Vec<With<'a>>,
-    expr: Option<Box<dyn ToQuery + 'a>>,
+    body: B,
+}
+
+/// iterate over values. e.g. `Vec<T>` is rendered as `{ a, b }`
+pub fn forin<'a, T, P, F, U>(values: T, body: F) -> ForInBuilder<'a>
+where
+    T: ToQueryArg + 'a,
+    P: ForPattern,
+    F: FnOnce(P) -> U,
+    U: ToQuery + 'a,
+{
+    ForInBuilder::new(values).body(body)
 }
 
-pub fn forin<'a>(arr_name: &'a str, elem_name: &'a str) -> ForInBuilder<'a> {
-    ForInBuilder::new(arr_name, elem_name)
+/// iterate over a set expression
+pub fn forin_expr<'a, T, P, F, U>(set: T, body: F) -> ForInBuilder<'a>
+where
+    T: ToQuery + 'a,
+    P: ForPattern,
+    F: FnOnce(P) -> U,
+    U: ToQuery + 'a,
+{
+    ForInBuilder::new_expr(set).body(body)
 }
 
-impl<'a> ForInBuilder<'a> {
-    pub fn new(arr_name: &'a str, elem_name: &'a str) -> Self {
+impl<'a> ForInBuilder<'a, NoBody> {
+    pub fn new<T>(values: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        Self::with_set(Either::Left(Arc::new(values)))
+    }
+
+    pub fn new_expr<T>(set: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self::with_set(Either::Right(Arc::new(set)))
+    }
+
+    fn with_set(set: QueryArgOrExpr<'a>) -> Self {
         Self {
-            arr: arr_name,
-            elem: elem_name,
-            expr: None,
+            elem: Cow::Borrowed("x"),
+            set,
             withs: Vec::new(),
+            body: NoBody,
         }
     }
 
-    pub fn with(mut self, with: With<'a>) -> Self {
-        self.withs.push(with);
+    /// name of element. default is `x`
+    pub fn bind(mut self, elem: impl Into<Cow<'a, str>>) -> Self {
+        self.elem = elem.into();
 
         self
     }
 
-    pub fn expr<T>(mut self, expr: T) -> Self
+    pub fn body<P, F, U>(self, body: F) -> ForInBuilder<'a>
+    where
+        P: ForPattern,
+        F: FnOnce(P) -> U,
+        U: ToQuery + 'a,
+    {
+        let expr = body(P::bind(&self.elem));
+
+        self.expr(expr)
+    }
+
+    pub fn expr<T>(self, expr: T) -> ForInBuilder<'a>
     where
         T: ToQuery + 'a,
     {
-        self.expr.replace(Box::new(expr));
+        ForInBuilder {
+            elem: self.elem,
+            set: self.set,
+            withs: self.withs,
+            body: Body(Arc::new(expr)),
+        }
+    }
+}
+
+impl<'a, B> ForInBuilder<'a, B> {
+    pub fn with(mut self, with: With<'a>) -> Self {
+        self.withs.push(with);
 
         self
     }
 }
 
 impl<'a> ToQuery for ForInBuilder<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::For(Box::new(ast::For {
+            withs: self.withs.iter().map(With::to_with_item).collect(),
+            binding: self.elem.as_ref().into(),
+            set: arg_or_expr_ast(&self.set),
+            body: self.body.0.to_ast(),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        check_withs(&self.withs)?;
+        validate_arg_or_expr(&self.set)?;
 
+        self.body.0.validate()
+    }
+}
+
+/// element of `for`, or a part of it
+#[derive(Clone)]
+pub struct ForElem(String);
+
+impl ForElem {
+    pub fn new(elem: impl Into<String>) -> Self {
+        Self(elem.into())
+    }
+
+    /// `x.0`
+    pub fn get(&self, index: usize) -> Self {
+        Self(format!("{}.{index}", self.0))
+    }
+
+    /// `x.name` of named tuple
+    pub fn field(&self, name: &str) -> Self {
+        Self(format!("{}.{name}", self.0))
+    }
+}
+
+impl fmt::Display for ForElem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<ForElem> for Cow<'_, str> {
+    fn from(elem: ForElem) -> Self {
+        Cow::Owned(elem.0)
+    }
+}
+
+impl ToQuery for ForElem {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Raw(self.0.as_str().into())
+    }
+}
+
+impl ToQueryArg for ForElem {
+    fn to_query_arg(&self) -> String {
+        self.0.clone()
+    }
+}
+
+/// binding of the element in the body closure
+///
+/// EdgeQL has no destructuring in `for`, so tuple patterns are bound to `x.0`, `x.1`, ...
+pub trait ForPattern: Sized {
+    fn bind(elem: &str) -> Self;
+}
+
+impl ForPattern for ForElem {
+    fn bind(elem: &str) -> Self {
+        ForElem::new(elem)
+    }
+}
+
+macro_rules! impl_for_pattern_for_tuple {
+    ($($index:tt $name:ident),+) => {
+        impl<$($name,)+> ForPattern for ($($name,)+)
+        where
+            $(
+                $name: ForPattern,
+            )+
         {
-            if !self.withs.is_empty() {
-                push_withs(q, self.withs.iter(), indent);
+            fn bind(elem: &str) -> Self {
+                ($($name::bind(&format!("{elem}.{}", $index)),)+)
             }
         }
+    };
+}
 
-        push_str(q, "for ", indent);
-        q.push_str(self.elem);
-        q.push_str(" in ");
-        q.push_str(self.arr);
-        q.push_str(" union ");
-
-        q.push('(');
-        q.push('\n');
+impl_for_pattern_for_tuple![0 T1];
+impl_for_pattern_for_tuple![0 T1, 1 T2];
+impl_for_pattern_for_tuple![0 T1, 1 T2, 2 T3];
+impl_for_pattern_for_tuple![0 T1, 1 T2, 2 T3, 3 T4];
+impl_for_pattern_for_tuple![0 T1, 1 T2, 2 T3, 3 T4, 4 T5];
+impl_for_pattern_for_tuple![0 T1, 1 T2, 2 T3, 3 T4, 4 T5, 5 T6];
 
-        let expr = self
-            .expr
-            .as_ref()
-            .expect("not set `expr` from ForInBuilder");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        q.push_str(&expr.to_query_with_indent(2 + indent));
+    #[test]
+    fn print() {
+        let query = forin(
+            vec![(1, "a"), (2, "b")],
+            |(uid, title): (ForElem, ForElem)| insert("Book").set("uid", uid).set("title", title),
+        )
+        .to_query();
 
-        q.push('\n');
+        assert_eq!(
+            query,
+            "\
+for x in { (1,<str>'a',), (2,<str>'b',) } union (
+  insert Book {
+    uid := x.0,
+    title := x.1,
+  }
+)"
+        );
 
-        push_str(q, ")", indent);
+        let query = ForInBuilder::new_expr(select("Book", crate::fields! { uid, title }))
+            .bind("book")
+            .body(|book: ForElem| {
+                update("Book")
+                    .filter(filter().add(AND, ".uid = $?", book.field("uid")))
+                    .set("title", Assign::Replace, book.field("title"))
+            })
+            .to_query();
 
-        qx
+        assert_eq!(
+            query,
+            "\
+for book in (
+  select Book {
+    uid,
+    title,
+  }
+) union (
+  update Book
+  filter
+    .uid = book.uid
+  set {
+    title := book.title,
+  }
+)"
+        );
     }
 }
@@ -0,0 +1,237 @@
+use std::borrow::Cow;
+
+use either::Either;
+
+use super::*;
+
+/// `name(arg, ..., named := arg)`
+///
+/// ```ignore
+/// fields! {
+///     tag_count := count(raw(".book_tags")),
+///     lower_title := str_lower(raw(".title")),
+///     released_month := datetime_truncate(raw(".released_at"), "months"),
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Func<'a> {
+    name: Cow<'a, str>,
+    args: Vec<(Option<Cow<'a, str>>, QueryArgOrExpr<'a>)>,
+}
+
+pub fn func<'a>(name: impl Into<Cow<'a, str>>) -> Func<'a> {
+    Func::new(name)
+}
+
+impl<'a> Func<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn arg<T>(mut self, value: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        self.args.push((None, Either::Left(Arc::new(value))));
+
+        self
+    }
+
+    pub fn arg_expr<T>(mut self, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.args.push((None, Either::Right(Arc::new(expr))));
+
+        self
+    }
+
+    /// `name := value`
+    pub fn named_arg<T>(mut self, name: impl Into<Cow<'a, str>>, value: T) -> Self
+    where
+        T: ToQueryArg + 'a,
+    {
+        self.args
+            .push((Some(name.into()), Either::Left(Arc::new(value))));
+
+        self
+    }
+
+    /// `name := (expr)`
+    pub fn named_arg_expr<T>(mut self, name: impl Into<Cow<'a, str>>, expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.args
+            .push((Some(name.into()), Either::Right(Arc::new(expr))));
+
+        self
+    }
+}
+
+impl<'a> ToQuery for Func<'a> {
+    /// written inline if every argument fits in a line
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let args = self.args.iter().map(|(name, x)| {
+            let arg = match x {
+                Either::Left(value) => ast::Expr::Literal(value.to_query_arg().into()),
+                Either::Right(expr) => expr.to_ast(),
+            };
+
+            (name.as_deref().map(Cow::Borrowed), arg)
+        });
+
+        ast::Expr::Call(Box::new(ast::Call {
+            name: self.name.as_ref().into(),
+            args: args.collect(),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.args
+            .iter()
+            .try_for_each(|(_, x)| validate_arg_or_expr(x))
+    }
+}
+
+macro_rules! unary_funcs {
+    ($($(#[$meta:meta])* $ident:ident => $name:literal $(,)?)*) => {
+        $(
+            $(#[$meta])*
+            pub fn $ident<'a, T>(expr: T) -> Func<'a>
+            where
+                T: ToQuery + 'a,
+            {
+                Func::new($name).arg_expr(expr)
+            }
+        )*
+    };
+}
+
+unary_funcs! {
+    count => "count",
+    sum => "sum",
+    min => "min",
+    max => "max",
+    array_agg => "array_agg",
+    array_unpack => "array_unpack",
+    len => "len",
+    str_lower => "str_lower",
+    str_upper => "str_upper",
+    str_trim => "str_trim",
+    /// `to_str(expr)`. format can be added by `.arg("YYYY-MM-DD")`
+    to_str => "to_str",
+    to_int64 => "to_int64",
+}
+
+pub fn datetime_current<'a>() -> Func<'a> {
+    Func::new("datetime_current")
+}
+
+/// `datetime_truncate(expr, 'days')`
+pub fn datetime_truncate<'a, T>(expr: T, unit: &'a str) -> Func<'a>
+where
+    T: ToQuery + 'a,
+{
+    Func::new("datetime_truncate").arg_expr(expr).arg(unit)
+}
+
+/// `datetime_get(expr, 'year')`
+pub fn datetime_get<'a, T>(expr: T, element: &'a str) -> Func<'a>
+where
+    T: ToQuery + 'a,
+{
+    Func::new("datetime_get").arg_expr(expr).arg(element)
+}
+
+/// `re_test(pattern, expr)`
+pub fn re_test<'a, T>(pattern: &'a str, expr: T) -> Func<'a>
+where
+    T: ToQuery + 'a,
+{
+    Func::new("re_test").arg(pattern).arg_expr(expr)
+}
+
+/// `contains(haystack, needle)`
+pub fn contains<'a, T, U>(haystack: T, needle: U) -> Func<'a>
+where
+    T: ToQuery + 'a,
+    U: ToQueryArg + 'a,
+{
+    Func::new("contains").arg_expr(haystack).arg(needle)
+}
+
+pub mod math {
+    use super::*;
+
+    unary_funcs! {
+        abs => "math::abs",
+        ceil => "math::ceil",
+        floor => "math::floor",
+        ln => "math::ln",
+        mean => "math::mean",
+        stddev => "math::stddev",
+        var => "math::var",
+    }
+}
+
+pub mod cal {
+    use super::*;
+
+    unary_funcs! {
+        to_local_date => "cal::to_local_date",
+        to_local_datetime => "cal::to_local_datetime",
+        to_local_time => "cal::to_local_time",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let fields = crate::fields! {
+            title,
+            lower_title := str_lower(raw(".title")),
+            tag_count := count(select("BookTag", []).filter(filter().add(AND, ".book = $?", raw("Book")))),
+            released_month := datetime_truncate(raw(".released_at"), "months"),
+            mean_price := math::mean(raw(".editions.price")),
+        };
+
+        let query = select("Book", fields)
+            .filter(filter().add_expr(AND, re_test("^a", str_lower(raw(".title")))))
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  title,
+  lower_title := (
+    str_lower(.title)
+  ),
+  tag_count := (
+    count(
+      (
+        select BookTag
+        filter
+          .book = Book
+      )
+    )
+  ),
+  released_month := (
+    datetime_truncate(.released_at, <str>'months')
+  ),
+  mean_price := (
+    math::mean(.editions.price)
+  ),
+}
+filter
+  re_test(<str>'^a', str_lower(.title))"
+        );
+    }
+}
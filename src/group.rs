@@ -1,86 +1,115 @@
-use std::fmt::Debug;
+use std::{borrow::Cow, fmt::Debug, marker::PhantomData};
 
 use edgedb_protocol::queryable::Queryable;
 
-use super::{push_str, ToQuery};
+use super::{ast, check_withs, BuildError, ToQuery, TypeName, Untyped, With};
 
-#[derive(Clone)]
-pub struct GroupBuilder<'a> {
-    target: &'a str,
-    fields: &'a str,
-    by: Vec<String>,
-    using: Vec<String>,
+/// `Out` is the result type inferred by `query`. usually `GroupResult<K, T>`
+pub struct GroupBuilder<'a, Out = Untyped> {
+    target: Cow<'a, str>,
+    fields: Cow<'a, str>,
+    withs: Vec<With<'a>>,
+    by: Vec<Cow<'a, str>>,
+    using: Vec<Cow<'a, str>>,
+    out: PhantomData<fn() -> Out>,
 }
 
-pub fn group<'a>(target: &'a str, fields: &'a str) -> GroupBuilder<'a> {
+pub fn group<'a>(
+    target: impl Into<Cow<'a, str>>,
+    fields: impl Into<Cow<'a, str>>,
+) -> GroupBuilder<'a> {
     GroupBuilder::new(target, fields)
 }
 
+/// target is `T::type_name()`. results are `GroupResult<K, T>`
+pub fn group_as<'a, K, T>(fields: impl Into<Cow<'a, str>>) -> GroupBuilder<'a, GroupResult<K, T>>
+where
+    T: TypeName,
+{
+    GroupBuilder::new(T::type_name(), fields).returns()
+}
+
 impl<'a> GroupBuilder<'a> {
-    pub fn new(target: &'a str, fields: &'a str) -> Self {
+    pub fn new(target: impl Into<Cow<'a, str>>, fields: impl Into<Cow<'a, str>>) -> Self {
         Self {
-            target,
-            fields,
+            target: target.into(),
+            fields: fields.into(),
+            withs: Vec::new(),
             by: Vec::new(),
             using: Vec::new(),
+            out: PhantomData,
         }
     }
+}
 
-    pub fn using(mut self, using: impl ToString) -> Self {
-        self.using.push(using.to_string());
+impl<'a, Out> GroupBuilder<'a, Out> {
+    /// result type of `query`, `query_single` and `query_required_single`
+    pub fn returns<T>(self) -> GroupBuilder<'a, T> {
+        GroupBuilder {
+            target: self.target,
+            fields: self.fields,
+            withs: self.withs,
+            by: self.by,
+            using: self.using,
+            out: PhantomData,
+        }
+    }
+
+    pub fn with(mut self, with: With<'a>) -> Self {
+        self.withs.push(with);
 
         self
     }
 
-    pub fn by(mut self, by: impl ToString) -> Self {
-        self.by.push(by.to_string());
+    pub fn using(mut self, using: impl Into<Cow<'a, str>>) -> Self {
+        self.using.push(using.into());
 
         self
     }
-}
 
-impl<'a> ToQuery for GroupBuilder<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let target = self.target;
-        let fields = self.fields;
+    /// a name or a `Path`. e.g. `.author.name`
+    pub fn by(mut self, by: impl Into<Cow<'a, str>>) -> Self {
+        self.by.push(by.into());
 
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        push_str(q, "group ", indent);
-        q.push_str(target);
+        self
+    }
+}
 
-        if !fields.is_empty() {
-            q.push_str(" { ");
-            q.push_str(fields);
-            q.push_str(" }");
+// derive would require `Out: Clone`
+impl<'a, Out> Clone for GroupBuilder<'a, Out> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target.clone(),
+            fields: self.fields.clone(),
+            withs: self.withs.clone(),
+            by: self.by.clone(),
+            using: self.using.clone(),
+            out: PhantomData,
         }
+    }
+}
 
-        // using
-
-        if !self.using.is_empty() {
-            q.push('\n');
+impl<'a, Out> ToQuery for GroupBuilder<'a, Out> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Group(Box::new(ast::Group {
+            withs: self.withs.iter().map(With::to_with_item).collect(),
+            subject: self.target.as_ref().into(),
+            shape: Some(self.fields.as_ref().into()).filter(|x: &Cow<str>| !x.is_empty()),
+            using: self.using.iter().map(|x| x.as_ref().into()).collect(),
+            by: self.by.iter().map(|x| x.as_ref().into()).collect(),
+        }))
+    }
 
-            let using = self.using.join(", ");
+    fn validate(&self) -> Result<(), BuildError> {
+        check_withs(&self.withs)?;
 
-            push_str(q, "using", indent);
-            q.push('\n');
-            push_str(q, &using, 2 + indent);
+        if self.by.is_empty() {
+            return Err(BuildError::NoGroupBy {
+                target: self.target.to_string(),
+            });
         }
 
-        // by
-
-        q.push('\n');
-
-        let by = self.by.join(", ");
-
-        push_str(q, "by", indent);
-
-        q.push('\n');
-
-        push_str(q, &by, 2 + indent);
-
-        qx
+        Ok(())
     }
 }
 
@@ -242,3 +271,56 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// only type checked. result types are inferred from builders
+    #[allow(dead_code)]
+    async fn typed(client: &edgedb_tokio::Client) -> Result<(), Error> {
+        let _titles: Vec<String> = select_expr(raw("Book.title"))
+            .returns::<String>()
+            .query(client)
+            .await?;
+
+        let _groups: Vec<GroupResult<String, String>> = group("Book", "")
+            .by(".kind")
+            .returns::<GroupResult<String, String>>()
+            .query(client)
+            .await?;
+
+        // untyped builders still name the result at the call site
+        let _title = select_expr(raw("Book.title"))
+            .query_required_single::<String>(client)
+            .await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn print() {
+        let query = group("Book", "")
+            .by(".kind")
+            .returns::<GroupResult<String, String>>()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+group Book
+by
+  .kind"
+        );
+    }
+
+    #[test]
+    fn no_by() {
+        assert_eq!(
+            group("Book", "").try_to_query(),
+            Err(BuildError::NoGroupBy {
+                target: "Book".to_string()
+            })
+        );
+    }
+}
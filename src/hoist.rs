@@ -0,0 +1,636 @@
+use super::*;
+
+/// lifts identical subqueries into one `with` binding
+///
+/// only uncorrelated selects (`select Type ...`) are lifted. a select is correlated if it names
+/// a for-variable, a binding of a nested with block or the subject of an enclosing statement.
+/// hoisted bindings are numbered per render and placed after the bindings they reference
+///
+/// ```ignore
+/// let me = select("User", []).filter(filter().add(AND, ".id = $?", uid));
+///
+/// // with hoisted_0 := (select User filter .id = ...)
+/// // select Book { liked := .likes in hoisted_0 } filter .owner = hoisted_0
+/// hoist(select("Book", fields! { liked := ... me.clone() ... }).filter(... me ...));
+/// ```
+#[derive(Clone)]
+pub struct Hoist<'a> {
+    query: Arc<dyn ToQuery + 'a>,
+}
+
+pub fn hoist<'a, T>(query: T) -> Hoist<'a>
+where
+    T: ToQuery + 'a,
+{
+    Hoist::new(query)
+}
+
+impl<'a> Hoist<'a> {
+    pub fn new<T>(query: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self {
+            query: Arc::new(query),
+        }
+    }
+}
+
+/// names bound around a subquery, except by the root with block
+#[derive(Clone, Default)]
+struct Scope {
+    names: Vec<String>,
+    /// nested `module module` changes what every name refers to
+    module: bool,
+}
+
+impl Scope {
+    /// scope of children of `expr`. `withs` is false for the root, whose bindings stay visible
+    fn enter(&self, expr: &ast::Expr, withs: bool) -> Self {
+        let mut scope = self.clone();
+
+        match expr {
+            ast::Expr::Select(x) => scope.names.extend(subject_names(&x.subject)),
+            ast::Expr::Update(x) => scope.names.extend(subject_names(&x.subject)),
+            ast::Expr::Insert(x) => scope.names.extend(names_in(&x.subject)),
+            ast::Expr::Delete(x) => scope.names.extend(names_in(&x.subject)),
+            ast::Expr::Group(x) => scope.names.extend(names_in(&x.subject)),
+            ast::Expr::For(x) => scope.names.push(x.binding.to_string()),
+            _ => return scope,
+        }
+
+        if withs {
+            let withs = expr.withs().unwrap_or_default();
+
+            scope.module |= withs
+                .iter()
+                .any(|x| matches!(x, ast::WithItem::Module { alias: None, .. }));
+            scope.names.extend(with_names(withs));
+        }
+
+        scope
+    }
+
+    /// `select Type ...` whose free names are not bound by this scope
+    fn is_hoistable(&self, expr: &ast::Expr, free: &[String]) -> bool {
+        let ast::Expr::Select(select) = expr else {
+            return false;
+        };
+
+        let ast::Expr::Raw(subject) = &select.subject else {
+            return false;
+        };
+
+        subject.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && !self.module
+            && !free.iter().any(|x| self.names.contains(x))
+    }
+}
+
+/// names in EdgeQL text. paths from the subject like `.id` and string literals are skipped
+fn names_in(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut prev = ' ';
+
+    while let Some(c) = chars.next() {
+        if c == '\'' || c == '"' {
+            // skip to the closing quote
+            while let Some(x) = chars.next() {
+                match x {
+                    '\\' => {
+                        chars.next();
+                    }
+                    x if x == c => break,
+                    _ => {}
+                }
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = c.to_string();
+
+            while let Some(x) = chars.next_if(|x| x.is_alphanumeric() || *x == '_') {
+                name.push(x);
+            }
+
+            if prev != '.' {
+                names.push(name);
+            }
+        }
+
+        prev = c;
+    }
+
+    names
+}
+
+/// names a subject makes visible to the rest of its statement. a subquery makes none
+fn subject_names(subject: &ast::Expr) -> Vec<String> {
+    match subject {
+        ast::Expr::Raw(x) => names_in(x),
+        _ => Vec::new(),
+    }
+}
+
+/// names bound by a with block
+fn with_names<'a>(withs: &'a [ast::WithItem]) -> impl Iterator<Item = String> + 'a {
+    withs.iter().filter_map(|x| match x {
+        ast::WithItem::Binding { name, .. } => Some(name.to_string()),
+        ast::WithItem::Module { alias, .. } => alias.as_ref().map(|x| x.to_string()),
+    })
+}
+
+/// names bound by `expr` for its children
+fn bound_names(expr: &ast::Expr) -> Vec<String> {
+    let mut names = with_names(expr.withs().unwrap_or_default()).collect::<Vec<_>>();
+
+    if let ast::Expr::For(x) = expr {
+        names.push(x.binding.to_string());
+    }
+
+    names
+}
+
+/// names written by `expr` itself, not by its subexpressions.
+/// strings, parameters, field names and function names name no binding
+fn own_names(expr: &ast::Expr) -> Vec<String> {
+    let mut names = Vec::new();
+
+    match expr {
+        // a literal may be EdgeQL too, e.g. the element of a for loop
+        ast::Expr::Raw(x) | ast::Expr::Literal(x) => names.extend(names_in(x)),
+        ast::Expr::Rendered(x) => {
+            for part in &x.text.pretty {
+                names.extend(names_in(part));
+            }
+
+            for slot in &x.slots {
+                if let Either::Left(name) = slot {
+                    names.extend(names_in(name));
+                }
+            }
+        }
+        ast::Expr::Select(x) => condition_names(x.filter.as_ref(), &mut names),
+        ast::Expr::Insert(x) => {
+            names.extend(names_in(&x.subject));
+
+            if let Some(on) = x.unless_conflict.as_ref().and_then(|x| x.on.as_ref()) {
+                names.extend(names_in(on));
+            }
+        }
+        ast::Expr::Update(x) => condition_names(x.filter.as_ref(), &mut names),
+        ast::Expr::Delete(x) => {
+            names.extend(names_in(&x.subject));
+            condition_names(x.filter.as_ref(), &mut names);
+        }
+        ast::Expr::Group(x) => {
+            names.extend(names_in(&x.subject));
+
+            for text in x.shape.iter().chain(&x.using).chain(&x.by) {
+                names.extend(names_in(text));
+            }
+        }
+        ast::Expr::Condition(x) => condition_names(Some(x), &mut names),
+        ast::Expr::IfElse(x) => {
+            for (cond, _) in &x.branches {
+                condition_names(Some(cond), &mut names);
+            }
+        }
+        ast::Expr::UnlessConflict(x) => {
+            if let Some(on) = &x.on {
+                names.extend(names_in(on));
+            }
+        }
+        _ => {}
+    }
+
+    names
+}
+
+/// names in the raw terms of a condition. terms holding an expression are subexpressions
+fn condition_names(cond: Option<&ast::Condition>, names: &mut Vec<String>) {
+    let Some(cond) = cond else {
+        return;
+    };
+
+    for (_, term) in &cond.terms {
+        match term {
+            ast::Term::Raw(raw) | ast::Term::Arg { raw, .. } => names.extend(names_in(raw)),
+            ast::Term::Expr(_) => {}
+            ast::Term::Group(cond) => condition_names(Some(cond), names),
+        }
+    }
+}
+
+/// what the first pass found out about a node, in pre-order
+#[derive(Clone, Copy)]
+struct Node {
+    hoistable: bool,
+    /// nodes in the subtree, this one included
+    size: usize,
+}
+
+#[derive(Default)]
+struct Analysis<'a> {
+    nodes: Vec<Node>,
+    /// hoistable subqueries, how often each occurs and the names free in it
+    counts: Vec<(ast::Expr<'a>, usize, Vec<String>)>,
+    /// every name written or bound anywhere in the query
+    used: Vec<String>,
+}
+
+/// first pass. returns the names free in `expr`, collected from the tree in one walk
+fn analyze<'a>(
+    expr: &mut ast::Expr<'a>,
+    scope: &Scope,
+    analysis: &mut Analysis<'a>,
+) -> Vec<String> {
+    let index = analysis.nodes.len();
+    analysis.nodes.push(Node {
+        hoistable: false,
+        size: 1,
+    });
+
+    let bound = bound_names(expr);
+    let mut free = own_names(expr);
+
+    analysis.used.extend(free.iter().chain(&bound).cloned());
+
+    let inner = scope.enter(expr, true);
+    expr.visit_children_mut(&mut |x| free.extend(analyze(x, &inner, analysis)));
+
+    // a binding of a nested with block shadows the same name outside
+    free.retain(|x| !bound.contains(x));
+    free.sort_unstable();
+    free.dedup();
+
+    let hoistable = scope.is_hoistable(expr, &free);
+
+    analysis.nodes[index] = Node {
+        hoistable,
+        size: analysis.nodes.len() - index,
+    };
+
+    if hoistable {
+        match analysis.counts.iter_mut().find(|(x, ..)| x == expr) {
+            Some((_, n, _)) => *n += 1,
+            None => analysis.counts.push((expr.clone(), 1, free.clone())),
+        }
+    }
+
+    free
+}
+
+/// second pass. replaces the outermost repeated subqueries by their binding
+fn replace<'a>(
+    expr: &mut ast::Expr<'a>,
+    analysis: &Analysis<'a>,
+    index: &mut usize,
+    names: &mut dyn Iterator<Item = String>,
+    hoisted: &mut Vec<(ast::Expr<'a>, String, Vec<String>)>,
+) {
+    let node = analysis.nodes[*index];
+
+    let repeated = analysis
+        .counts
+        .iter()
+        .find(|(x, n, _)| node.hoistable && *n > 1 && x == expr);
+
+    if let Some((_, _, free)) = repeated {
+        let name = match hoisted.iter().find(|(x, ..)| x == expr) {
+            Some((_, name, _)) => name.clone(),
+            None => {
+                let name = names.next().unwrap_or_default();
+                hoisted.push((expr.clone(), name.clone(), free.clone()));
+
+                name
+            }
+        };
+
+        *expr = ast::Expr::Raw(name.into());
+        *index += node.size;
+
+        return;
+    }
+
+    *index += 1;
+
+    expr.visit_children_mut(&mut |x| replace(x, analysis, index, names, hoisted));
+}
+
+/// `withs` with each hoisted binding after the last binding it references
+fn place<'a>(
+    withs: Vec<ast::WithItem<'a>>,
+    hoisted: Vec<(ast::Expr<'a>, String, Vec<String>)>,
+) -> Vec<ast::WithItem<'a>> {
+    let mut slots = vec![Vec::new(); withs.len() + 1];
+
+    for (expr, name, names) in hoisted {
+        let after = withs
+            .iter()
+            .rposition(|x| matches!(x, ast::WithItem::Binding { name, .. } if names.iter().any(|x| x == name)))
+            .map_or(0, |i| i + 1);
+
+        slots[after].push(ast::WithItem::Binding {
+            name: name.into(),
+            value: ast::Expr::Paren(Box::new(expr)),
+        });
+    }
+
+    let mut slots = slots.into_iter();
+    let mut placed = slots.next().unwrap_or_default();
+
+    for (with, hoisted) in withs.into_iter().zip(slots) {
+        placed.push(with);
+        placed.extend(hoisted);
+    }
+
+    placed
+}
+
+impl<'a> ToQuery for Hoist<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let mut query = self.query.to_ast();
+        let scope = Scope::default().enter(&query, false);
+
+        let mut analysis = Analysis {
+            used: own_names(&query)
+                .into_iter()
+                .chain(bound_names(&query))
+                .collect(),
+            ..Analysis::default()
+        };
+        query.visit_children_mut(&mut |x| {
+            analyze(x, &scope, &mut analysis);
+        });
+
+        if !analysis.counts.iter().any(|(_, n, _)| *n > 1) {
+            return query;
+        }
+
+        // `hoisted_0..n` in order of appearance, skipping names the query already uses
+        let mut names = (0..)
+            .map(|i| format!("hoisted_{i}"))
+            .filter(|x| !analysis.used.contains(x));
+
+        let mut index = 0;
+        let mut hoisted = Vec::new();
+        query.visit_children_mut(&mut |x| {
+            replace(x, &analysis, &mut index, &mut names, &mut hoisted)
+        });
+
+        match query.withs_mut() {
+            Some(withs) => {
+                *withs = place(std::mem::take(withs), hoisted);
+
+                query
+            }
+            None => ast::Expr::Select(Box::new(ast::Select {
+                withs: place(Vec::new(), hoisted),
+                ..ast::Select::new(ast::Expr::Paren(Box::new(query)))
+            })),
+        }
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.query.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let me = select("User", []).filter(filter().add(AND, ".id = $?", 1234));
+
+        let fields = crate::fields! {
+            title,
+            owner := me.clone(),
+        };
+
+        let query =
+            hoist(select("Book", fields).filter(filter().add_expr(AND, exists(me.clone()))))
+                .to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  hoisted_0 := (
+    select User
+    filter
+      .id = 1234
+  ),
+select Book {
+  title,
+  owner := (
+    hoisted_0
+  ),
+}
+filter
+  exists (
+    hoisted_0
+  )"
+        );
+
+        let query = hoist(
+            select("Book", crate::fields! { owner := me.clone() })
+                .with(with_expr("me", me.clone()))
+                .filter(filter().add_expr(AND, exists(me))),
+        )
+        .to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  hoisted_0 := (
+    select User
+    filter
+      .id = 1234
+  ),
+  me := (
+    hoisted_0
+  ),
+select Book {
+  owner := (
+    hoisted_0
+  ),
+}
+filter
+  exists (
+    hoisted_0
+  )"
+        );
+    }
+
+    #[test]
+    fn correlated() {
+        let owner = |book: &ForElem| {
+            select("User", []).filter(filter().add(AND, ".id = $?", book.field("owner")))
+        };
+
+        let query = hoist(
+            ForInBuilder::new_expr(select("Book", crate::fields! { owner }))
+                .bind("book")
+                .body(|book: ForElem| {
+                    insert("Like")
+                        .set_expr("user", owner(&book))
+                        .set_expr("friend", owner(&book))
+                }),
+        )
+        .to_query();
+
+        assert_eq!(
+            query,
+            "\
+for book in (
+  select Book {
+    owner,
+  }
+) union (
+  insert Like {
+    user := (
+      select User
+      filter
+        .id = book.owner
+    ),
+    friend := (
+      select User
+      filter
+        .id = book.owner
+    ),
+  }
+)"
+        );
+    }
+
+    #[test]
+    fn bindings() {
+        let me = select("User", []).filter(filter().add(AND, ".id = $?", raw("uid")));
+
+        let query = hoist(
+            select("Book", crate::fields! { owner := me.clone() })
+                .with(with("hoisted_0", 1))
+                .with(with("uid", 5))
+                .filter(filter().add_expr(AND, exists(me))),
+        );
+
+        assert_eq!(query.to_query(), query.to_query());
+
+        let query = query.to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  hoisted_0 := 1,
+  uid := 5,
+  hoisted_1 := (
+    select User
+    filter
+      .id = uid
+  ),
+select Book {
+  owner := (
+    hoisted_1
+  ),
+}
+filter
+  exists (
+    hoisted_1
+  )"
+        );
+    }
+
+    #[test]
+    fn literal() {
+        // the name of the for-variable in a string is no reference to it
+        let owner = select("User", []).filter(filter().add(AND, ".name = $?", "book's owner"));
+
+        let query = hoist(
+            ForInBuilder::new_expr(select("Book", crate::fields! { owner }))
+                .bind("book")
+                .body(|_: ForElem| {
+                    insert("Like")
+                        .set_expr("user", owner.clone())
+                        .set_expr("friend", owner.clone())
+                }),
+        )
+        .to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  hoisted_0 := (
+    select User
+    filter
+      .name = <str>'book\\'s owner'
+  ),
+for book in (
+  select Book {
+    owner,
+  }
+) union (
+  insert Like {
+    user := (
+      hoisted_0
+    ),
+    friend := (
+      hoisted_0
+    ),
+  }
+)"
+        );
+    }
+
+    #[test]
+    fn shadowed() {
+        // `uid` is bound by the subquery itself, not by the for loop around it
+        let me = select("User", []).with(with("uid", 5)).filter(filter().add(
+            AND,
+            ".id = $?",
+            raw("uid"),
+        ));
+
+        let query = hoist(
+            ForInBuilder::new_expr(select("Book", crate::fields! { owner }))
+                .bind("uid")
+                .body(|_: ForElem| {
+                    insert("Like")
+                        .set_expr("user", me.clone())
+                        .set_expr("friend", me.clone())
+                }),
+        )
+        .to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  hoisted_0 := (
+    with
+      uid := 5,
+    select User
+    filter
+      .id = uid
+  ),
+for uid in (
+  select Book {
+    owner,
+  }
+) union (
+  insert Like {
+    user := (
+      hoisted_0
+    ),
+    friend := (
+      hoisted_0
+    ),
+  }
+)"
+        );
+    }
+}
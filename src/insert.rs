@@ -1,11 +1,13 @@
+use std::borrow::Cow;
+
 use either::Either;
 
 use super::*;
 
 #[derive(Clone)]
 pub struct OnConflict<'a> {
-    field: Option<&'a str>,
-    else_expr: Option<Box<dyn ToQuery + 'a>>,
+    field: Option<Cow<'a, str>>,
+    else_expr: Option<Arc<dyn ToQuery + 'a>>,
 }
 
 pub fn on_conflict<'a>() -> OnConflict<'a> {
@@ -30,8 +32,8 @@ impl<'a> OnConflict<'a> {
         self
     }
 
-    pub fn field(mut self, field: &'a str) -> Self {
-        self.field.replace(field);
+    pub fn field(mut self, field: impl Into<Cow<'a, str>>) -> Self {
+        self.field.replace(field.into());
 
         self
     }
@@ -40,10 +42,17 @@ impl<'a> OnConflict<'a> {
     where
         T: ToQuery + 'a,
     {
-        self.else_expr.replace(Box::new(expr));
+        self.else_expr.replace(Arc::new(expr));
 
         self
     }
+
+    pub(crate) fn to_unless_conflict(&self) -> ast::UnlessConflict<'_> {
+        ast::UnlessConflict {
+            on: self.field.as_deref().map(Cow::Borrowed),
+            else_expr: self.else_expr.as_ref().map(|expr| expr.to_ast()),
+        }
+    }
 }
 
 impl<'a> Default for OnConflict<'a> {
@@ -52,53 +61,51 @@ impl<'a> Default for OnConflict<'a> {
     }
 }
 
+/// `unless conflict ...` clause alone
 impl<'a> ToQuery for OnConflict<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        push_str(q, "unless conflict", indent);
-
-        if let (Some(field), Some(expr)) = (self.field, self.else_expr.as_ref()) {
-            q.push(' ');
-            q.push_str("on");
-            q.push(' ');
-
-            q.push_str(field);
-
-            q.push('\n');
-            push_str(q, "else", indent);
-            q.push(' ');
-            q.push('(');
-            q.push('\n');
-
-            q.push_str(&expr.to_query_with_indent(2 + indent));
-            q.push('\n');
-            push(q, ')', indent);
-        }
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::UnlessConflict(Box::new(self.to_unless_conflict()))
+    }
 
-        qx
+    fn validate(&self) -> Result<(), BuildError> {
+        self.else_expr.iter().try_for_each(|x| x.validate())
     }
 }
 
 #[derive(Clone)]
 pub struct InsertBuilder<'a> {
-    target: &'a str,
+    target: Cow<'a, str>,
     withs: Vec<With<'a>>,
-    values: Vec<(&'a str, Assign, QueryArgOrExpr<'a>)>,
+    module: Option<Cow<'a, str>>,
+    values: Vec<(Cow<'a, str>, Assign, QueryArgOrExpr<'a>)>,
+    /// field and type
+    casts: Vec<(Cow<'a, str>, Cow<'a, str>)>,
     on_conflict: Option<OnConflict<'a>>,
 }
 
-pub fn insert(target: &str) -> InsertBuilder {
+pub fn insert<'a>(target: impl Into<Cow<'a, str>>) -> InsertBuilder<'a> {
     InsertBuilder::new(target)
 }
 
+/// `insert Type { ... }` from struct `Type`. `None` fields are skipped
+#[cfg(feature = "serde")]
+pub fn insert_struct<T>(value: &T) -> Result<InsertBuilder<'static>, BuildError>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let (name, values) = payload::assignments(value, payload::Nulls::Skip)?;
+
+    Ok(InsertBuilder::new(name).push_payload(values))
+}
+
 impl<'a> InsertBuilder<'a> {
-    pub fn new(target: &'a str) -> Self {
+    pub fn new(target: impl Into<Cow<'a, str>>) -> Self {
         Self {
-            target,
+            target: target.into(),
             withs: Vec::new(),
+            module: None,
             values: Vec::new(),
+            casts: Vec::new(),
             on_conflict: None,
         }
     }
@@ -109,22 +116,37 @@ impl<'a> InsertBuilder<'a> {
         self
     }
 
-    pub fn set<T>(mut self, field: &'a str, v: T) -> Self
+    /// default module. `with module module`
+    pub fn module(mut self, module: impl Into<Cow<'a, str>>) -> Self {
+        self.module.replace(module.into());
+
+        self
+    }
+
+    pub fn set<T>(mut self, field: impl Into<Cow<'a, str>>, v: T) -> Self
     where
         T: ToQueryArg + 'a,
     {
         self.values
-            .push((field, Assign::Replace, Either::Left(Box::new(v))));
+            .push((field.into(), Assign::Replace, Either::Left(Arc::new(v))));
 
         self
     }
 
-    pub fn set_expr<T>(mut self, field: &'a str, v: T) -> Self
+    pub fn set_expr<T>(mut self, field: impl Into<Cow<'a, str>>, v: T) -> Self
     where
         T: ToQuery + 'a,
     {
         self.values
-            .push((field, Assign::Replace, Either::Right(Box::new(v))));
+            .push((field.into(), Assign::Replace, Either::Right(Arc::new(v))));
+
+        self
+    }
+
+    /// `<ty>` before the value of `field`. e.g. `datetime` or `uuid` for strings of a struct
+    /// payload, which are `<str>` otherwise. `author.born` is a field of an inserted `author`
+    pub fn cast(mut self, field: impl Into<Cow<'a, str>>, ty: impl Into<Cow<'a, str>>) -> Self {
+        self.casts.push((field.into(), ty.into()));
 
         self
     }
@@ -134,36 +156,78 @@ impl<'a> InsertBuilder<'a> {
 
         self
     }
-}
 
-impl<'a> ToQuery for InsertBuilder<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
+    /// every field of struct `value`. see `payload` for the mapping
+    #[cfg(feature = "serde")]
+    pub fn set_struct<T>(self, value: &T, nulls: payload::Nulls) -> Result<Self, BuildError>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let (_, values) = payload::assignments(value, nulls)?;
+
+        Ok(self.push_payload(values))
+    }
+
+    #[cfg(feature = "serde")]
+    fn push_payload(mut self, values: payload::Fields) -> Self {
+        for (field, value) in values {
+            let value: QueryArgOrExpr<'a> = match value {
+                Either::Left(value) => Either::Left(Arc::new(value)),
+                Either::Right(expr) => Either::Right(Arc::new(expr)),
+            };
 
-        // with
-        {
-            push_withs(q, self.withs.iter(), indent);
+            self.values.push((field.into(), Assign::Replace, value));
         }
 
-        push_str(q, "insert", indent);
-        q.push(' ');
-        q.push_str(self.target);
-        q.push(' ');
+        self
+    }
+}
 
-        // set values
-        {
-            push_object(q, &self.values, indent);
+impl<'a> ToQuery for InsertBuilder<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let module = self.module.as_deref().map(|module| ast::WithItem::Module {
+            alias: None,
+            module: module.into(),
+        });
+
+        let mut assignments: Vec<_> = self
+            .values
+            .iter()
+            .map(|(field, assign, value)| ast::Assignment {
+                name: field.as_ref().into(),
+                assign: *assign,
+                value: arg_or_expr_ast(value),
+            })
+            .collect();
+
+        for (field, ty) in &self.casts {
+            cast_assignment(&mut assignments, field, ty);
         }
 
-        // on conflict
-        if let Some(on_conflict) = &self.on_conflict {
-            q.push('\n');
+        ast::Expr::Insert(Box::new(ast::Insert {
+            withs: module
+                .into_iter()
+                .chain(self.withs.iter().map(With::to_with_item))
+                .collect(),
+            subject: self.target.as_ref().into(),
+            assignments,
+            unless_conflict: self
+                .on_conflict
+                .as_ref()
+                .map(OnConflict::to_unless_conflict),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        let module = self.module.clone().map(With::module);
+
+        check_withs(module.iter().chain(&self.withs))?;
 
-            q.push_str(&on_conflict.to_query_with_indent(indent));
+        for (_, _, value) in &self.values {
+            validate_arg_or_expr(value)?;
         }
 
-        qx
+        self.on_conflict.iter().try_for_each(OnConflict::validate)
     }
 }
 
@@ -0,0 +1,165 @@
+//! `query_as_json` deserializing JSON results into serde types
+
+use serde::de::DeserializeOwned;
+
+use super::*;
+
+#[async_trait::async_trait]
+pub trait JsonExecution: Sized {
+    /// `Error::Decode` with the JSON path if a result does not fit `T`
+    async fn query_as_json<'a, T: DeserializeOwned>(
+        self,
+        connection: impl Into<Connection<'a>> + Send,
+    ) -> Result<Vec<T>, Error>;
+
+    async fn query_single_as_json<'a, T: DeserializeOwned>(
+        self,
+        connection: impl Into<Connection<'a>> + Send,
+    ) -> Result<Option<T>, Error>;
+}
+
+#[async_trait::async_trait]
+impl<Q> JsonExecution for Q
+where
+    Q: ToQuery,
+{
+    async fn query_as_json<'a, T: DeserializeOwned>(
+        self,
+        connection: impl Into<Connection<'a>> + Send,
+    ) -> Result<Vec<T>, Error> {
+        let prepared = Prepared::new(&self)?;
+        let connection = connection.into();
+        let json = query_elapsed! {
+            execute_prepared!(connection, prepared, query_json)
+        }
+        .map_err(|err| prepared.error(err))?;
+
+        prepared.decode(&json)
+    }
+
+    async fn query_single_as_json<'a, T: DeserializeOwned>(
+        self,
+        connection: impl Into<Connection<'a>> + Send,
+    ) -> Result<Option<T>, Error> {
+        let prepared = Prepared::new(&self)?;
+        let connection = connection.into();
+        let json = query_elapsed! {
+            execute_prepared!(connection, prepared, query_single_json)
+        }
+        .map_err(|err| prepared.error(err))?;
+
+        json.map(|json| prepared.decode(&json)).transpose()
+    }
+}
+
+impl Prepared<'_> {
+    fn decode<T: DeserializeOwned>(&self, json: &str) -> Result<T, Error> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+            let redacted = printer::print_redacted(&self.ast);
+
+            Error::Decode(JsonError::new(
+                err,
+                self.query.clone(),
+                redacted,
+                self.args.clone(),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Book {
+        title: String,
+        author: Author,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Author {
+        name: String,
+    }
+
+    #[test]
+    fn decode() {
+        let query = select("Book", crate::fields! { title, author: { name } })
+            .filter(filter().add(AND, ".title = $?", "secret"));
+
+        let prepared = Prepared::new(&query).unwrap();
+
+        let books: Vec<Book> = prepared
+            .decode(r#"[{"title": "a", "author": {"name": "b"}}]"#)
+            .unwrap();
+
+        assert_eq!(
+            books,
+            [Book {
+                title: "a".to_string(),
+                author: Author {
+                    name: "b".to_string()
+                }
+            }]
+        );
+
+        let err = prepared
+            .decode::<Vec<Book>>(r#"[{"title": "a", "author": {"name": 1}}]"#)
+            .unwrap_err();
+
+        let message = err.to_string();
+
+        assert_eq!(
+            message,
+            "\
+invalid result: at `[0].author.name`: invalid type: integer `1`, expected a string at line 1 column 36
+query:
+select Book {
+  title,
+  author: {
+    name,
+  },
+}
+filter
+  .title = $?"
+        );
+
+        let Error::Decode(err) = err else {
+            panic!("{err}");
+        };
+
+        assert_eq!(err.path(), "[0].author.name");
+
+        let query = err.query();
+        let redacted = err.redacted_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  title,
+  author: {
+    name,
+  },
+}
+filter
+  .title = <str>'secret'"
+        );
+        assert_eq!(
+            redacted,
+            "\
+select Book {
+  title,
+  author: {
+    name,
+  },
+}
+filter
+  .title = $?"
+        );
+    }
+}
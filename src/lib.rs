@@ -1,29 +1,64 @@
 //! QueryBuilder 만들 때 주의 점
-//! - 소괄호로 해당 쿼리를 감쌀 때는 해당 쿼리 빌더 안에서 해야함. 바깥 빌더에서 소괄호를 감싸면 indent에 문제 생김
+//! - 빌더는 문자열 대신 `ast::Expr`를 만들고, 출력은 `printer`에서만 함
+//! - 하위 쿼리를 소괄호로 감쌀 때는 `ast::Expr::Paren`을 쓰면 indent는 printer가 맞춰줌
+mod assertion;
+pub mod ast;
+mod conditional;
+mod delete;
+mod error;
 mod field;
 mod filter;
 mod forin;
+mod func;
 mod group;
+mod hoist;
 mod insert;
+#[cfg(feature = "serde")]
+mod json;
 mod order_by;
+#[cfg(feature = "serde")]
+mod patch;
+mod path;
+#[cfg(feature = "serde")]
+mod payload;
+mod printer;
 mod select;
+mod set;
+mod soft_delete;
+mod template;
 mod update;
 mod with;
 
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt, sync::Arc};
 
+pub use assertion::*;
+pub use conditional::*;
+pub use delete::*;
+pub use error::*;
 pub use field::*;
 pub use filter::*;
 pub use forin::*;
+pub use func::*;
 pub use group::*;
+pub use hoist::*;
 pub use insert::*;
+#[cfg(feature = "serde")]
+pub use json::*;
 pub use order_by::*;
+#[cfg(feature = "serde")]
+pub use patch::*;
+pub use path::*;
+#[cfg(feature = "serde")]
+pub use payload::Nulls;
 pub use select::*;
+pub use set::*;
+pub use soft_delete::*;
+pub use template::*;
 pub use update::*;
 pub use with::*;
 
 use dyn_clone::{clone_trait_object, DynClone};
-use edgedb_protocol::{model::LocalDatetime, queryable::Queryable};
+use edgedb_protocol::{model::LocalDatetime, queryable::Queryable, value::Value};
 use either::Either;
 use iter_tools::Itertools;
 use tap::Tap;
@@ -47,40 +82,7 @@ macro_rules! queryable {
 
 pub const ARG_IDENTITY: &str = "$?";
 
-fn push(q: &mut String, char: char, indent: usize) {
-    for _ in 0..indent {
-        q.push(' ');
-    }
-
-    q.push(char);
-}
-
-fn push_str(q: &mut String, string: &str, indent: usize) {
-    for _ in 0..indent {
-        q.push(' ');
-    }
-
-    q.push_str(string);
-}
-
-fn push_withs<'a>(q: &mut String, withs: impl IntoIterator<Item = &'a With<'a>>, indent: usize) {
-    let mut withs = withs.into_iter();
-
-    if let Some(first) = withs.next() {
-        push_str(q, "with", indent);
-        q.push('\n');
-        q.push_str(&first.to_query_with_indent(2 + indent));
-        q.push(',');
-        q.push('\n');
-    }
-
-    for with in withs {
-        q.push_str(&with.to_query_with_indent(2 + indent));
-        q.push(',');
-        q.push('\n');
-    }
-}
-
+#[cfg(test)]
 fn push_fields<'a>(q: &mut String, fields: impl IntoIterator<Item = Field<'a>>, indent: usize) {
     for field in fields {
         q.push_str(&field.to_query_with_indent(indent));
@@ -88,70 +90,54 @@ fn push_fields<'a>(q: &mut String, fields: impl IntoIterator<Item = Field<'a>>,
     }
 }
 
-fn push_filter<'a>(q: &mut String, filter: Option<&'a Filter<'a>>, indent: usize) {
-    if let Some(filter) = filter {
-        if filter.is_empty() {
-            return;
-        }
-
-        push_str(q, "filter", indent);
-        q.push('\n');
-
-        q.push_str(&filter.to_query_with_indent(indent));
+/// value as literal, expression wrapped by parentheses
+fn arg_or_expr_ast<'q>(value: &'q QueryArgOrExpr<'_>) -> ast::Expr<'q> {
+    match value {
+        Either::Left(value) => ast::Expr::Literal(value.to_query_arg().into()),
+        Either::Right(expr) => ast::Expr::Paren(Box::new(expr.to_ast())),
     }
 }
 
-fn push_object<'a>(
-    q: &mut String,
-    obj: impl IntoIterator<Item = &'a (&'a str, Assign, QueryArgOrExpr<'a>)>,
-    indent: usize,
-) {
-    q.push('{');
-
-    for (field, assign, value) in obj {
-        q.push('\n');
-        push_str(q, field, 2 + indent);
+/// `<ty>` before the value assigned to `path`. `a.b` is field `b` of objects inserted into `a`
+fn cast_assignment<'q>(assignments: &mut [ast::Assignment<'q>], path: &'q str, ty: &'q str) {
+    let (name, rest) = match path.split_once('.') {
+        Some((name, rest)) => (name, Some(rest)),
+        None => (path, None),
+    };
 
-        q.push(' ');
+    for assignment in assignments.iter_mut().filter(|x| x.name == name) {
+        match rest {
+            Some(rest) => cast_nested(&mut assignment.value, rest, ty),
+            None => {
+                let value = std::mem::replace(&mut assignment.value, ast::Expr::Seq(Vec::new()));
 
-        match assign {
-            Assign::Add => {
-                q.push_str("+=");
-            }
-            Assign::Remove => {
-                q.push_str("-=");
-            }
-            Assign::Replace => {
-                q.push_str(":=");
+                assignment.value =
+                    ast::Expr::Seq(vec![ast::Expr::Raw(format!("<{ty}>").into()), value]);
             }
         }
+    }
+}
 
-        q.push(' ');
-
-        match value {
-            Either::Left(value) => {
-                q.push_str(&value.to_query_arg());
-            }
-
-            Either::Right(expr) => {
-                q.push('(');
-                q.push('\n');
-
-                q.push_str(&expr.to_query_with_indent(4 + indent));
-
-                q.push('\n');
-                push(q, ')', 2 + indent);
-            }
+/// inserts in `expr`, also inside parentheses and unions
+fn cast_nested<'q>(expr: &mut ast::Expr<'q>, path: &'q str, ty: &'q str) {
+    match expr {
+        ast::Expr::Insert(x) => cast_assignment(&mut x.assignments, path, ty),
+        ast::Expr::Paren(_) | ast::Expr::Set(_) => {
+            expr.visit_children_mut(&mut |x| cast_nested(x, path, ty))
         }
-
-        q.push(',');
+        _ => {}
     }
+}
 
-    q.push('\n');
-    push(q, '}', indent);
+/// nested expression is validated, value is not
+fn validate_arg_or_expr(value: &QueryArgOrExpr) -> Result<(), BuildError> {
+    match value {
+        Either::Left(_) => Ok(()),
+        Either::Right(expr) => expr.validate(),
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Assign {
     /// +=
     Add,
@@ -161,16 +147,35 @@ pub enum Assign {
     Replace,
 }
 
+impl Assign {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Assign::Add => "+=",
+            Assign::Remove => "-=",
+            Assign::Replace => ":=",
+        }
+    }
+}
+
 /// tag.<book_tags[is Book]
 pub fn backlink(target: &str, property: &str, is: &str) -> String {
-    format!("{target}.<{property}[is {is}]")
+    path(target).backlink(property).is(is).to_string()
 }
 
 pub trait TypeName {
     fn type_name() -> &'static str;
+
+    /// soft-delete policy of the type. applied by `select_as`, `update_as` and `delete_as`
+    fn soft_delete() -> Option<SoftDelete<'static>> {
+        None
+    }
 }
 
-type QueryArgOrExpr<'a> = Either<Box<dyn ToQueryArg + 'a>, Box<dyn ToQuery + 'a>>;
+/// result type of builders not given by `returns`. `query::<T>()` names it at the call site
+#[derive(Clone, Copy, Debug)]
+pub struct Untyped;
+
+type QueryArgOrExpr<'a> = Either<Arc<dyn ToQueryArg + 'a>, Arc<dyn ToQuery + 'a>>;
 
 #[derive(Clone)]
 pub struct Raw<'a>(Cow<'a, str>);
@@ -186,13 +191,8 @@ impl<'a> Raw<'a> {
 }
 
 impl<'a> ToQuery for Raw<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        push_str(q, &self.0, indent);
-
-        qx
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Raw(self.0.as_ref().into())
     }
 }
 
@@ -203,19 +203,115 @@ impl<'a> ToQueryArg for Raw<'a> {
 }
 
 pub trait ToQuery: DynClone + Send + Sync {
-    fn to_query_with_indent(&self, indent: usize) -> String;
+    /// tree borrowing names and raw text from the builder
+    fn to_ast(&self) -> ast::Expr<'_>;
+
+    fn to_query_with_indent(&self, indent: usize) -> String {
+        printer::print(&self.to_ast(), indent)
+    }
 
     fn to_query(&self) -> String {
         self.to_query_with_indent(0)
             .tap(|query| tracing::debug!("\n{query}"))
     }
+
+    /// renders into `w` instead of a new `String`. the tree is still built as for `to_query`
+    fn write_query(&self, w: &mut dyn fmt::Write) -> fmt::Result {
+        printer::write(w, &self.to_ast(), 0, false)
+    }
+
+    /// one line without indentation. e.g. for logs, metrics and cache keys
+    fn to_query_compact(&self) -> String {
+        printer::print_compact(&self.to_ast())
+    }
+
+    /// checked by `try_to_query` and `QueryExecution` before rendering
+    fn validate(&self) -> Result<(), BuildError> {
+        Ok(())
+    }
+
+    /// also refuses a `slot` outside of a template
+    fn try_to_query(&self) -> Result<String, BuildError> {
+        self.validate()?;
+
+        let mut ast = self.to_ast();
+
+        template::arguments(&mut ast)?;
+
+        Ok(printer::print(&ast, 0).tap(|query| tracing::debug!("\n{query}")))
+    }
 }
 
 clone_trait_object!(ToQuery);
 
 impl<T: Clone + ToQuery> ToQuery for &T {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        (*self).to_query_with_indent(indent)
+    fn to_ast(&self) -> ast::Expr<'_> {
+        (*self).to_ast()
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        (*self).validate()
+    }
+}
+
+/// rewritten tree can be rendered again
+impl ToQuery for ast::Expr<'_> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        self.clone()
+    }
+}
+
+impl fmt::Display for ast::Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        printer::write(f, self, 0, false)
+    }
+}
+
+/// `Display` same as `to_query`
+macro_rules! impl_display {
+    ($($ty:ident $(,)?)*) => {
+        $(
+            impl fmt::Display for $ty<'_> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    self.write_query(f)
+                }
+            }
+        )*
+    };
+}
+
+impl_display![
+    Raw,
+    Filter,
+    Field,
+    OrderBy,
+    With,
+    OnConflict,
+    InsertBuilder,
+    UpdateBuilder,
+    DeleteBuilder,
+    ForInBuilder,
+    IfElse,
+    Coalesce,
+    Exists,
+    SetExpr,
+    Distinct,
+    Assert,
+    Func,
+    Hoist,
+    Slot,
+    Bound,
+];
+
+impl<Out> fmt::Display for SelectBuilder<'_, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_query(f)
+    }
+}
+
+impl<Out> fmt::Display for GroupBuilder<'_, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_query(f)
     }
 }
 
@@ -224,29 +320,69 @@ pub trait QueryExecution: Sized {
     async fn query<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Vec<T>, edgedb_tokio::Error>;
+    ) -> Result<Vec<T>, Error>;
 
     async fn query_single<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Option<T>, edgedb_tokio::Error>;
+    ) -> Result<Option<T>, Error>;
 
-    async fn query_json<'a>(
+    /// `Error::NoData` if nothing returned
+    async fn query_required_single<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<edgedb_protocol::model::Json, edgedb_tokio::Error>;
+    ) -> Result<T, Error>;
 
-    async fn query_single_json<'a>(
+    async fn query_json<'a>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Option<edgedb_protocol::model::Json>, edgedb_tokio::Error>;
+    ) -> Result<edgedb_protocol::model::Json, Error>;
 
-    async fn execute<'a>(
+    async fn query_single_json<'a>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<(), edgedb_tokio::Error>;
+    ) -> Result<Option<edgedb_protocol::model::Json>, Error>;
+
+    async fn execute<'a>(self, connection: impl Into<Connection<'a>> + Send) -> Result<(), Error>;
+}
+
+/// `query`, `query_single` and `query_required_single` inferring `T` from `Out` of builder.
+/// inherent methods are chosen over `QueryExecution`, which is still used for untyped builders
+macro_rules! impl_typed_execution {
+    ($($ty:ident $(,)?)*) => {
+        $(
+            impl<'a, Out> $ty<'a, Out>
+            where
+                Out: Queryable + Send,
+            {
+                pub async fn query<'c>(
+                    self,
+                    connection: impl Into<Connection<'c>> + Send,
+                ) -> Result<Vec<Out>, Error> {
+                    QueryExecution::query::<Out>(self, connection).await
+                }
+
+                pub async fn query_single<'c>(
+                    self,
+                    connection: impl Into<Connection<'c>> + Send,
+                ) -> Result<Option<Out>, Error> {
+                    QueryExecution::query_single::<Out>(self, connection).await
+                }
+
+                /// `Error::NoData` if nothing returned
+                pub async fn query_required_single<'c>(
+                    self,
+                    connection: impl Into<Connection<'c>> + Send,
+                ) -> Result<Out, Error> {
+                    QueryExecution::query_required_single::<Out>(self, connection).await
+                }
+            }
+        )*
+    };
 }
 
+impl_typed_execution![SelectBuilder, GroupBuilder];
+
 /// for query exectuion
 #[macro_export]
 macro_rules! query_elapsed {
@@ -279,6 +415,55 @@ impl<'a> From<&'a mut edgedb_tokio::Transaction> for Connection<'a> {
     }
 }
 
+/// calls `$method` of the connection with the query and arguments of `$prepared`
+macro_rules! execute_prepared {
+    ($connection:ident, $prepared:ident, $($method:tt)+) => {
+        match ($connection, &$prepared.args) {
+            (Connection::Client(x), None) => x.$($method)+(&$prepared.query, &()).await,
+            (Connection::Client(x), Some(args)) => x.$($method)+(&$prepared.query, args).await,
+            (Connection::Transaction(x), None) => x.$($method)+(&$prepared.query, &()).await,
+            (Connection::Transaction(x), Some(args)) => {
+                x.$($method)+(&$prepared.query, args).await
+            }
+        }
+    };
+}
+
+/// path for `json`, whose `mod` comes before this macro
+#[cfg(feature = "serde")]
+pub(crate) use execute_prepared;
+
+/// validated query kept with its tree, which is rendered again only for errors
+struct Prepared<'q> {
+    ast: ast::Expr<'q>,
+    query: String,
+    /// values of `$name` parameters. `None` if the query has none
+    args: Option<Value>,
+}
+
+impl<'q> Prepared<'q> {
+    fn new(query: &'q impl ToQuery) -> Result<Self, BuildError> {
+        query.validate()?;
+
+        let mut ast = query.to_ast();
+        let args = template::arguments(&mut ast)?;
+        let query = printer::print(&ast, 0).tap(|query| tracing::debug!("\n{query}"));
+
+        Ok(Self { ast, query, args })
+    }
+
+    fn error(&self, err: edgedb_tokio::Error) -> Error {
+        let redacted = printer::print_redacted(&self.ast);
+
+        Error::execution(QueryError::new(
+            err,
+            self.query.clone(),
+            redacted,
+            self.args.clone(),
+        ))
+    }
+}
+
 #[async_trait::async_trait]
 impl<Q> QueryExecution for Q
 where
@@ -287,91 +472,81 @@ where
     async fn query<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Vec<T>, edgedb_tokio::Error> {
+    ) -> Result<Vec<T>, Error> {
+        let prepared = Prepared::new(&self)?;
         let connection = connection.into();
         query_elapsed! {
-            match connection {
-                Connection::Client(x) => {
-                    x.query::<T, _>(&self.to_query(), &()).await
-                }
-                Connection::Transaction(x) => {
-                    x.query::<T, _>(&self.to_query(), &()).await
-                }
-            }
+            execute_prepared!(connection, prepared, query::<T, _>)
         }
+        .map_err(|err| prepared.error(err))
     }
 
     async fn query_single<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Option<T>, edgedb_tokio::Error> {
+    ) -> Result<Option<T>, Error> {
+        let prepared = Prepared::new(&self)?;
         let connection = connection.into();
         query_elapsed! {
-            match connection {
-                Connection::Client(x) => {
-                    x.query_single::<T, _>(&self.to_query(), &()).await
-                }
-                Connection::Transaction(x) => {
-                    x.query_single::<T, _>(&self.to_query(), &()).await
-                }
-            }
+            execute_prepared!(connection, prepared, query_single::<T, _>)
         }
+        .map_err(|err| prepared.error(err))
     }
 
-    async fn query_json<'a>(
+    async fn query_required_single<'a, T: Queryable + Send>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<edgedb_protocol::model::Json, edgedb_tokio::Error> {
+    ) -> Result<T, Error> {
+        let prepared = Prepared::new(&self)?;
         let connection = connection.into();
         query_elapsed! {
-            match connection {
-                Connection::Client(x) => {
-                    x.query_json(&self.to_query(), &()).await
-                }
-                Connection::Transaction(x) => {
-                    x.query_json(&self.to_query(), &()).await
-                }
-            }
+            execute_prepared!(connection, prepared, query_required_single::<T, _>)
         }
+        .map_err(|err| prepared.error(err))
     }
 
-    async fn query_single_json<'a>(
+    async fn query_json<'a>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<Option<edgedb_protocol::model::Json>, edgedb_tokio::Error> {
+    ) -> Result<edgedb_protocol::model::Json, Error> {
+        let prepared = Prepared::new(&self)?;
         let connection = connection.into();
         query_elapsed! {
-            match connection {
-                Connection::Client(x) => {
-                    x.query_single_json(&self.to_query(), &()).await
-                }
-                Connection::Transaction(x) => {
-                    x.query_single_json(&self.to_query(), &()).await
-                }
-            }
+            execute_prepared!(connection, prepared, query_json)
         }
+        .map_err(|err| prepared.error(err))
     }
 
-    async fn execute<'a>(
+    async fn query_single_json<'a>(
         self,
         connection: impl Into<Connection<'a>> + Send,
-    ) -> Result<(), edgedb_tokio::Error> {
+    ) -> Result<Option<edgedb_protocol::model::Json>, Error> {
+        let prepared = Prepared::new(&self)?;
         let connection = connection.into();
         query_elapsed! {
-            match connection {
-                Connection::Client(x) => {
-                    x.execute(&self.to_query(), &()).await
-                }
-                Connection::Transaction(x) => {
-                    x.execute(&self.to_query(), &()).await
-                }
-            }
+            execute_prepared!(connection, prepared, query_single_json)
         }
+        .map_err(|err| prepared.error(err))
+    }
+
+    async fn execute<'a>(self, connection: impl Into<Connection<'a>> + Send) -> Result<(), Error> {
+        let prepared = Prepared::new(&self)?;
+        let connection = connection.into();
+        query_elapsed! {
+            execute_prepared!(connection, prepared, execute)
+        }
+        .map_err(|err| prepared.error(err))
     }
 }
 
 pub trait ToQueryArg: DynClone + Send + Sync {
     fn to_query_arg(&self) -> String;
+
+    /// EdgeDB type and value when bound to a template slot.
+    /// `None` if it has no argument type, e.g. raw EdgeQL or a number out of `int64`
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        None
+    }
 }
 
 clone_trait_object!(ToQueryArg);
@@ -383,6 +558,10 @@ where
     fn to_query_arg(&self) -> String {
         (&self).to_query_arg()
     }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        (&self).to_query_param()
+    }
 }
 
 impl<T> ToQueryArg for &Vec<T>
@@ -394,24 +573,77 @@ where
 
         format!("{{ {r} }}")
     }
+
+    /// `array<ty>`, unpacked into a set where it is written. `None` if empty or nested,
+    /// because the element type is only known from an element and arrays of arrays are not allowed
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        let mut ty = None;
+        let mut values = Vec::with_capacity(self.len());
+
+        for x in self.iter() {
+            let (element, value) = x.to_query_param()?;
+
+            if matches!(value, Value::Array(_)) {
+                return None;
+            }
+
+            ty.get_or_insert(element);
+            values.push(value);
+        }
+
+        Some((format!("array<{}>", ty?).into(), Value::Array(values)))
+    }
 }
 
 impl ToQueryArg for String {
     fn to_query_arg(&self) -> String {
         self.as_str().to_query_arg()
     }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("str".into(), Value::Str(self.to_string())))
+    }
 }
 
 impl ToQueryArg for &String {
     fn to_query_arg(&self) -> String {
         self.as_str().to_query_arg()
     }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("str".into(), Value::Str(self.to_string())))
+    }
+}
+
+impl ToQueryArg for Cow<'_, str> {
+    fn to_query_arg(&self) -> String {
+        self.as_ref().to_query_arg()
+    }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("str".into(), Value::Str(self.to_string())))
+    }
 }
 
 impl ToQueryArg for &str {
     fn to_query_arg(&self) -> String {
-        let escaped_single_quote = self.replace('\'', "\\'");
-        format!("<str>'{escaped_single_quote}'")
+        // backslash first, or the escape of a quote would be escaped again
+        let escaped = self.replace('\\', "\\\\").replace('\'', "\\'");
+        format!("<str>'{escaped}'")
+    }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("str".into(), Value::Str(self.to_string())))
+    }
+}
+
+impl ToQueryArg for edgedb_protocol::model::Uuid {
+    fn to_query_arg(&self) -> String {
+        format!("<uuid>'{self}'")
+    }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("uuid".into(), Value::Uuid(*self)))
     }
 }
 
@@ -420,31 +652,86 @@ impl ToQueryArg for edgedb_protocol::model::Datetime {
         let datetime = LocalDatetime::from(*self);
         format!("<datetime>'{}T{}+00'", datetime.date(), datetime.time())
     }
+
+    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+        Some(("datetime".into(), Value::Datetime(*self)))
+    }
 }
 
 macro_rules! impl_to_query_arg {
-    ($($ty:ty $(,)?)*) => {
+    ($($ty:ty $(=> $param:literal $value:ident $($convert:ident $into:ty)?)?),* $(,)?) => {
         $(
             impl ToQueryArg for $ty {
                 fn to_query_arg(&self) -> String {
                     // format!("<{}>{self}", stringify!($ty))
                     self.to_string()
                 }
+
+                $(
+                    fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+                        Some(($param.into(), Value::$value(param_value!(*self $(, $convert $into)?))))
+                    }
+                )?
             }
         )*
     };
 }
 
-// fn cast_type(x: &str) -> Option<&str> {
-//     match x {
-//         "bool" => "bool",
-//         "f32" => "f32",
-//         "f64" => "f64",
-//         "Uuid" => "uuid"
-//     }
-// }
+/// `from` widens, `try_from` is `None` out of range
+macro_rules! param_value {
+    ($x:expr) => {
+        $x
+    };
+    ($x:expr, from $into:ty) => {
+        <$into>::from($x)
+    };
+    ($x:expr, try_from $into:ty) => {
+        <$into>::try_from($x).ok()?
+    };
+}
 
-impl_to_query_arg![i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, bool];
+impl_to_query_arg![
+    i8 => "int16" Int16 from i16,
+    i16 => "int16" Int16,
+    i32 => "int32" Int32,
+    i64 => "int64" Int64,
+    i128 => "int64" Int64 try_from i64,
+    isize => "int64" Int64 try_from i64,
+    u8 => "int16" Int16 from i16,
+    u16 => "int32" Int32 from i32,
+    u32 => "int64" Int64 from i64,
+    u64 => "int64" Int64 try_from i64,
+    u128 => "int64" Int64 try_from i64,
+    usize => "int64" Int64 try_from i64,
+    bool => "bool" Bool,
+];
+
+macro_rules! impl_to_query_arg_for_float {
+    ($($ty:ty => $param:literal $value:ident $(,)?)*) => {
+        $(
+            impl ToQueryArg for $ty {
+                fn to_query_arg(&self) -> String {
+                    // `NaN`, `inf` and `-inf` are only written as strings
+                    if !self.is_finite() {
+                        return format!("<{}>'{self}'", $param);
+                    }
+
+                    // `{:?}` keeps the fraction, e.g. `1.0`
+                    format!("{self:?}")
+                }
+
+                fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+                    Some(($param.into(), Value::$value(*self)))
+                }
+            }
+        )*
+    };
+}
+
+impl_to_query_arg_for_float![
+    f32 => "float32" Float32,
+    f64 => "float64" Float64,
+];
 
 macro_rules! impl_to_query_arg_for_tuple {
     ($($name:ident $(,)?)+) => {
@@ -472,6 +759,23 @@ macro_rules! impl_to_query_arg_for_tuple {
                 q
 
             }
+
+            /// `tuple<..>` if every element has an argument type
+            fn to_query_param(&self) -> Option<(Cow<'static, str>, Value)> {
+                #[allow(non_snake_case)]
+                let ($($name,)+) = self;
+                let mut types = Vec::new();
+                let mut values = Vec::new();
+
+                $(
+                    let (ty, value) = $name.to_query_param()?;
+
+                    types.push(ty);
+                    values.push(value);
+                )+
+
+                Some((format!("tuple<{}>", types.join(", ")).into(), Value::Tuple(values)))
+            }
         }
 };
 }
@@ -490,5 +794,4 @@ impl_to_query_arg_for_tuple![T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11];
 impl_to_query_arg_for_tuple![T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12];
 
 // TODO:
-// - f32, f64
 // - type cast <type>'aa'
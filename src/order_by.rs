@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 
-use super::{push_str, ToQuery};
+use super::{ast, ToQuery};
 
 // TODO: multiple argments 지원
 // 현재는 $?를 통해 인자 하나만 받을 수 있도록 구현돼있음
@@ -27,23 +27,25 @@ impl<'a> OrderBy<'a> {
     }
 }
 
-impl<'a> ToQuery for OrderBy<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
-
-        push_str(q, &self.by, 2 + indent);
-
-        if let Some(direction) = self.direction {
-            q.push(' ');
-            q.push_str(direction.as_str());
+impl<'a> OrderBy<'a> {
+    pub(crate) fn to_order(&self) -> ast::Order<'_> {
+        ast::Order {
+            by: ast::Expr::Raw(self.by.as_ref().into()),
+            direction: self.direction,
         }
+    }
+}
 
-        qx
+impl<'a> ToQuery for OrderBy<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        match self.direction {
+            Some(direction) => ast::Expr::Raw(format!("{} {}", self.by, direction.as_str()).into()),
+            None => ast::Expr::Raw(self.by.as_ref().into()),
+        }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum OrderDirection {
     Desc,
     Asc,
@@ -0,0 +1,346 @@
+//! RFC 7396 merge patch into `update` assignments
+//!
+//! - `null` is `{}`
+//! - strings are `<str>` unless cast by `property_as`, numbers and bools are written as-is
+//! - arrays replace the whole set. objects in them must be `{"id": ...}`, arrays are refused
+//! - `{"id": ...}` on a link is `select Type filter .id = <uuid>'...'`,
+//!   other objects update the linked object by `update .link set { ... }`
+
+use std::borrow::Cow;
+
+use either::Either;
+use serde_json::Value;
+
+use super::*;
+use crate::payload::FieldValue;
+
+#[derive(Clone, Debug)]
+enum Rule<'a> {
+    /// `<cast>` of strings
+    Property(Option<Cow<'a, str>>),
+    Link {
+        target: Cow<'a, str>,
+        fields: PatchFields<'a>,
+    },
+}
+
+/// fields which a merge patch may change. others are `BuildError::ForbiddenField`
+///
+/// ```ignore
+/// let fields = patch_fields()
+///     .property("title")
+///     .property_as("published", "datetime")
+///     .link("author", "Author", patch_fields().property("name"));
+///
+/// update("Book").filter(filter().add(AND, ".uid = $?", uid)).apply_patch(&body, &fields)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PatchFields<'a> {
+    fields: Vec<(Cow<'a, str>, Rule<'a>)>,
+}
+
+pub fn patch_fields<'a>() -> PatchFields<'a> {
+    PatchFields::new()
+}
+
+impl<'a> PatchFields<'a> {
+    pub fn new() -> Self {
+        Self { fields: Vec::new() }
+    }
+
+    pub fn property(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.fields.push((name.into(), Rule::Property(None)));
+
+        self
+    }
+
+    /// strings are cast. e.g. `<datetime><str>'...'`
+    pub fn property_as(
+        mut self,
+        name: impl Into<Cow<'a, str>>,
+        cast: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        self.fields
+            .push((name.into(), Rule::Property(Some(cast.into()))));
+
+        self
+    }
+
+    /// `target` is the type selected by `{"id": ...}`, `fields` are allowed in nested updates
+    pub fn link(
+        mut self,
+        name: impl Into<Cow<'a, str>>,
+        target: impl Into<Cow<'a, str>>,
+        fields: PatchFields<'a>,
+    ) -> Self {
+        self.fields.push((
+            name.into(),
+            Rule::Link {
+                target: target.into(),
+                fields,
+            },
+        ));
+
+        self
+    }
+
+    fn rule(&self, name: &str) -> Option<&Rule<'a>> {
+        self.fields
+            .iter()
+            .find_map(|(x, rule)| (x == name).then_some(rule))
+    }
+
+    /// `prefix` is the path of this object in the patch, e.g. `author.`
+    pub(crate) fn assignments(
+        &self,
+        patch: &Value,
+        prefix: &str,
+    ) -> Result<Vec<(String, FieldValue)>, BuildError> {
+        let Value::Object(patch) = patch else {
+            return Err(invalid(format!("`{prefix}` patch must be an object")));
+        };
+
+        let mut assignments = Vec::new();
+
+        for (name, value) in patch {
+            let path = format!("{prefix}{name}");
+
+            let Some(rule) = self.rule(name) else {
+                return Err(BuildError::ForbiddenField { path });
+            };
+
+            let value = match (rule, value) {
+                (_, Value::Null) => Either::Left(raw("{}")),
+                (Rule::Property(cast), value) => Either::Left(raw(literal(value, cast, &path)?)),
+                (Rule::Link { target, .. }, Value::Array(items)) => {
+                    Either::Right(link_set(target, items, &path)?)
+                }
+                (Rule::Link { target, fields }, Value::Object(object)) => match object.get("id") {
+                    Some(id) if object.len() == 1 => Either::Right(link(target, id, &path)?),
+                    Some(_) => return Err(invalid(format!("`{path}` mixes id with fields"))),
+                    None => {
+                        let nested = fields.assignments(value, &format!("{path}."))?;
+
+                        Either::Right(nested_update(name, nested).to_ast().into_owned())
+                    }
+                },
+                (Rule::Link { .. }, _) => {
+                    return Err(invalid(format!("`{path}` must be an object or an array")))
+                }
+            };
+
+            assignments.push((name.clone(), value));
+        }
+
+        Ok(assignments)
+    }
+}
+
+fn invalid(message: String) -> BuildError {
+    BuildError::InvalidPayload { message }
+}
+
+fn literal(value: &Value, cast: &Option<Cow<str>>, path: &str) -> Result<String, BuildError> {
+    let literal = match value {
+        Value::Null => "{}".to_string(),
+        Value::Bool(x) => x.to_string(),
+        Value::Number(x) => x.to_string(),
+        Value::String(x) => match cast {
+            Some(cast) => format!("<{cast}>{}", x.as_str().to_query_arg()),
+            None => x.as_str().to_query_arg(),
+        },
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .enumerate()
+                .map(|(i, x)| match x {
+                    // a set of sets is flattened by EdgeQL
+                    Value::Array(_) => Err(invalid(format!("`{path}[{i}]` is a nested array"))),
+                    x => literal(x, cast, &format!("{path}[{i}]")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            format!("{{ {} }}", items.join(", "))
+        }
+        Value::Object(_) => return Err(invalid(format!("`{path}` is not a link"))),
+    };
+
+    Ok(literal)
+}
+
+/// `select Type filter .id = <uuid>'...'`
+fn link(target: &str, id: &Value, path: &str) -> Result<ast::Expr<'static>, BuildError> {
+    let Value::String(id) = id else {
+        return Err(invalid(format!("`{path}.id` must be a string")));
+    };
+
+    let id = format!("<uuid>{}", id.as_str().to_query_arg());
+
+    Ok(select(target, [])
+        .filter(filter().add(AND, ".id = $?", raw(id)))
+        .to_ast()
+        .into_owned())
+}
+
+/// union of links by id
+fn link_set(target: &str, items: &[Value], path: &str) -> Result<ast::Expr<'static>, BuildError> {
+    let mut links = items.iter().enumerate().map(|(i, item)| {
+        let path = format!("{path}[{i}]");
+
+        match item {
+            Value::Object(object) if object.len() == 1 => match object.get("id") {
+                Some(id) => link(target, id, &path),
+                None => Err(invalid(format!("`{path}` must be {{\"id\": ...}}"))),
+            },
+            _ => Err(invalid(format!("`{path}` must be {{\"id\": ...}}"))),
+        }
+    });
+
+    let Some(first) = links.next().transpose()? else {
+        return Ok(ast::Expr::Raw("{}".into()));
+    };
+
+    let rest: Vec<_> = links
+        .map(|x| x.map(|x| (SetOperator::Union, x)))
+        .collect::<Result<_, _>>()?;
+
+    if rest.is_empty() {
+        return Ok(first);
+    }
+
+    Ok(ast::Expr::Set(Box::new(ast::Set { first, rest })))
+}
+
+/// `update .link set { ... }` of the linked object
+fn nested_update(name: &str, assignments: Vec<(String, FieldValue)>) -> UpdateBuilder<'static> {
+    let mut update = update(format!(".{name}")).all();
+
+    for (field, value) in assignments {
+        update = match value {
+            Either::Left(value) => update.set(field, Assign::Replace, value),
+            Either::Right(expr) => update.set_expr(field, Assign::Replace, expr),
+        };
+    }
+
+    update
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn print() {
+        let fields = patch_fields()
+            .property("title")
+            .property("price")
+            .property_as("published", "datetime")
+            .link("author", "Author", patch_fields().property("name"))
+            .link("tags", "BookTag", patch_fields());
+
+        let patch = json!({
+            "title": "it's",
+            "price": null,
+            "published": "2023-10-18T00:00:00+00:00",
+            "author": { "name": "b" },
+            "tags": [{ "id": "2ab4a8c0-6b54-11ee-b962-0242ac120002" }],
+        });
+
+        let query = update("Book")
+            .filter(filter().add(AND, ".uid = $?", 1234))
+            .apply_patch(&patch, &fields)
+            .unwrap()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+filter
+  .uid = 1234
+set {
+  author := (
+    update .author
+    set {
+      name := <str>'b',
+    }
+  ),
+  price := {},
+  published := <datetime><str>'2023-10-18T00:00:00+00:00',
+  tags := (
+    select BookTag
+    filter
+      .id = <uuid><str>'2ab4a8c0-6b54-11ee-b962-0242ac120002'
+  ),
+  title := <str>'it\\'s',
+}"
+        );
+
+        let forbidden = update("Book")
+            .all()
+            .apply_patch(&json!({ "author": { "email": "x" } }), &fields);
+
+        assert_eq!(
+            forbidden.err(),
+            Some(BuildError::ForbiddenField {
+                path: "author.email".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn array() {
+        let fields = patch_fields().property("genres");
+
+        let query = update("Book")
+            .all()
+            .apply_patch(&json!({ "genres": ["novel", "drama"] }), &fields)
+            .unwrap()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+set {
+  genres := { <str>'novel', <str>'drama' },
+}"
+        );
+
+        let nested = update("Book")
+            .all()
+            .apply_patch(&json!({ "genres": ["novel", ["drama"]] }), &fields);
+
+        assert_eq!(
+            nested.err(),
+            Some(BuildError::InvalidPayload {
+                message: "`genres[1]` is a nested array".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn escape() {
+        let fields = patch_fields().property("title");
+
+        // the backslash is escaped too, so the quote can not end the string
+        let patch = json!({ "title": "A\\', price := 0, owner := (select User limit 1) #" });
+
+        let query = update("Book")
+            .all()
+            .apply_patch(&patch, &fields)
+            .unwrap()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+set {
+  title := <str>'A\\\\\\', price := 0, owner := (select User limit 1) #',
+}"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{borrow::Cow, fmt};
+
+use super::*;
+
+#[derive(Clone)]
+enum Step<'a> {
+    /// .name
+    Prop(Cow<'a, str>),
+    /// .<name
+    Backlink(Cow<'a, str>),
+    /// [is Type]
+    Is(Cow<'a, str>),
+    /// @name
+    LinkProp(Cow<'a, str>),
+}
+
+/// ```ignore
+/// // .<book_tags[is Book].author.name
+/// path(None).backlink("book_tags").is("Book").prop("author").prop("name");
+///
+/// // Book.tags@weight
+/// path("Book").prop("tags").link_prop("weight");
+/// ```
+#[derive(Clone)]
+pub struct Path<'a> {
+    /// None if relative to subject of shape or filter
+    root: Option<Cow<'a, str>>,
+    steps: Vec<Step<'a>>,
+}
+
+pub fn path<'a>(root: impl OptionalName<'a>) -> Path<'a> {
+    Path::new(root)
+}
+
+impl<'a> Path<'a> {
+    pub fn new(root: impl OptionalName<'a>) -> Self {
+        Self {
+            root: root.into_name(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn prop(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.steps.push(Step::Prop(name.into()));
+
+        self
+    }
+
+    pub fn backlink(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.steps.push(Step::Backlink(name.into()));
+
+        self
+    }
+
+    /// type intersection
+    pub fn is(mut self, type_name: impl Into<Cow<'a, str>>) -> Self {
+        self.steps.push(Step::Is(type_name.into()));
+
+        self
+    }
+
+    pub fn link_prop(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.steps.push(Step::LinkProp(name.into()));
+
+        self
+    }
+}
+
+impl<'a> fmt::Display for Path<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(root) = &self.root {
+            f.write_str(root)?;
+        }
+
+        for step in &self.steps {
+            match step {
+                Step::Prop(name) => write!(f, ".{name}")?,
+                Step::Backlink(name) => write!(f, ".<{name}")?,
+                Step::Is(type_name) => write!(f, "[is {type_name}]")?,
+                Step::LinkProp(name) => write!(f, "@{name}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// for `order_by`, `Filter::add`, `GroupBuilder::by` and builder targets
+impl<'a> From<Path<'a>> for Cow<'a, str> {
+    fn from(path: Path<'a>) -> Self {
+        Cow::Owned(path.to_string())
+    }
+}
+
+impl<'a> ToQuery for Path<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Raw(self.to_string().into())
+    }
+}
+
+impl<'a> ToQueryArg for Path<'a> {
+    fn to_query_arg(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let author_name = path(None).prop("author").prop("name");
+        let books = path("tag").backlink("book_tags").is("Book");
+
+        assert_eq!(author_name.to_string(), ".author.name");
+        assert_eq!(books.to_string(), backlink("tag", "book_tags", "Book"));
+        assert_eq!(
+            path(None).prop("tags").link_prop("weight").to_string(),
+            ".tags@weight"
+        );
+
+        let query = select(
+            "Book",
+            crate::fields! { title, author_name := author_name.clone() },
+        )
+        .filter(filter().add(AND, format!("{author_name} = $?"), "someone"))
+        .order_by(order_by(author_name.clone(), ASC))
+        .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  title,
+  author_name := (
+    .author.name
+  ),
+}
+filter
+  .author.name = <str>'someone'
+order by
+  .author.name asc"
+        );
+
+        let query = update(books.clone().is("Novel"))
+            .filter(filter().add(AND, ".uid = $?", 1))
+            .set("released", Assign::Replace, true)
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update tag.<book_tags[is Book][is Novel]
+filter
+  .uid = 1
+set {
+  released := true,
+}"
+        );
+
+        let query = group("Book", "title").by(author_name).to_query();
+
+        assert_eq!(
+            query,
+            "\
+group Book { title }
+by
+  .author.name"
+        );
+    }
+}
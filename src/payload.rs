@@ -0,0 +1,906 @@
+//! struct payloads of `insert` and `update` by serde
+//!
+//! - strings are `<str>`, numbers and bools are written as-is, unit enum variants are `<Enum>'Variant'`
+//! - `Vec` is a set, tuple is a tuple
+//! - nested struct is `insert Type { ... }`, or `select Type filter .id = <uuid>'...'` if it has `id`
+//! - `id` of the payload itself is never assigned
+//! - in a diff, links are compared by `id` and lists of objects are updated by `+=` and `-=`.
+//!   changed fields of a linked object, or a changed nested object without `id`, are refused
+//!   instead of being dropped or inserted again. update those objects by their own query
+//!
+//! other scalars such as uuids and datetimes are serialized as strings.
+//! give their type by `.cast(field, "datetime")` of the insert or update builder
+
+use std::fmt;
+
+use either::Either;
+use serde::{ser, Serialize};
+
+use super::*;
+
+/// how `None` fields of a struct payload are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nulls {
+    /// leave the field out
+    Skip,
+    /// `field := {}`. clears the field on update
+    Empty,
+}
+
+/// literal or nested statement of a field
+pub(crate) type FieldValue = Either<Raw<'static>, ast::Expr<'static>>;
+
+pub(crate) type Fields = Vec<(&'static str, FieldValue)>;
+
+/// type name and fields of the struct `value`
+pub(crate) fn assignments<T>(value: &T, nulls: Nulls) -> Result<(&'static str, Fields), BuildError>
+where
+    T: Serialize + ?Sized,
+{
+    match value.serialize(ValueSerializer).map_err(BuildError::from)? {
+        Value::Struct { name, fields } => Ok((name, lower_fields(fields, nulls)?)),
+        _ => Err(invalid("payload must be a struct")),
+    }
+}
+
+/// changes from `original` to `modified` of the same struct
+pub(crate) struct Diff {
+    pub name: &'static str,
+    /// `<uuid>'...'` of `original`
+    pub id: Option<String>,
+    pub assignments: Vec<(&'static str, Assign, FieldValue)>,
+}
+
+pub(crate) fn diff<T>(original: &T, modified: &T) -> Result<Diff, BuildError>
+where
+    T: Serialize + ?Sized,
+{
+    let (Value::Struct { name, fields: old }, Value::Struct { fields: new, .. }) = (
+        original.serialize(ValueSerializer)?,
+        modified.serialize(ValueSerializer)?,
+    ) else {
+        return Err(invalid("payload must be a struct"));
+    };
+
+    let id = id_literal(&old);
+    let mut assignments = Vec::new();
+
+    // fields skipped by `skip_serializing_if` in `modified` only are cleared
+    for (field, value) in &old {
+        if *field != "id" && *value != Value::Null && !new.iter().any(|(x, _)| x == field) {
+            assignments.push((*field, Assign::Replace, Either::Left(raw("{}"))));
+        }
+    }
+
+    for (field, value) in new {
+        // fields skipped by `skip_serializing_if` in `original` are empty
+        let previous = old
+            .iter()
+            .find_map(|(x, value)| (*x == field).then_some(value))
+            .unwrap_or(&Value::Null);
+
+        if field == "id" {
+            continue;
+        }
+
+        check_nested(field, previous, &value)?;
+
+        if previous.same(&value) {
+            continue;
+        }
+
+        match (previous, value) {
+            (Value::List(previous), Value::List(items)) if has_link(previous, &items) => {
+                let added: Vec<_> = items
+                    .iter()
+                    .filter(|x| !previous.iter().any(|y| y.same(x)))
+                    .cloned()
+                    .collect();
+
+                let removed: Vec<_> = previous
+                    .iter()
+                    .filter(|x| !items.iter().any(|y| y.same(x)))
+                    .cloned()
+                    .collect();
+
+                if removed.iter().any(|x| x.id().is_none()) {
+                    return Err(invalid(format!("`{field}` removes an object without id")));
+                }
+
+                if !added.is_empty() {
+                    let value = lower(Value::List(added), Nulls::Empty)?;
+
+                    assignments.push((field, Assign::Add, value));
+                }
+
+                if !removed.is_empty() {
+                    let value = lower(Value::List(removed), Nulls::Empty)?;
+
+                    assignments.push((field, Assign::Remove, value));
+                }
+            }
+            (_, value) => {
+                assignments.push((field, Assign::Replace, lower(value, Nulls::Empty)?));
+            }
+        }
+    }
+
+    Ok(Diff {
+        name,
+        id,
+        assignments,
+    })
+}
+
+/// changes a diff cannot write. fields of an object linked by `id` would be dropped,
+/// and an object without `id` would be inserted again, leaving the old one behind
+fn check_nested(field: &str, previous: &Value, value: &Value) -> Result<(), BuildError> {
+    let (previous, items) = match (previous, value) {
+        (Value::List(a), Value::List(b)) => (a.as_slice(), b.as_slice()),
+        (Value::Struct { .. }, _) if previous.id().is_none() && previous != value => {
+            return Err(invalid(format!("`{field}` replaces an object without id")));
+        }
+        _ => (std::slice::from_ref(previous), std::slice::from_ref(value)),
+    };
+
+    let changed = previous
+        .iter()
+        .filter(|x| x.id().is_some())
+        .any(|x| items.iter().any(|y| x.same(y) && x != y));
+
+    if changed {
+        return Err(invalid(format!(
+            "`{field}` changes fields of a linked object"
+        )));
+    }
+
+    Ok(())
+}
+
+fn has_link(a: &[Value], b: &[Value]) -> bool {
+    a.iter().chain(b).any(|x| matches!(x, Value::Struct { .. }))
+}
+
+/// `<uuid>'...'` if `fields` has `id`
+fn id_literal(fields: &[(&'static str, Value)]) -> Option<String> {
+    fields.iter().find_map(|(field, value)| match value {
+        Value::Str(id) if *field == "id" => Some(format!("<uuid>{}", id.as_str().to_query_arg())),
+        _ => None,
+    })
+}
+
+fn invalid(message: impl ToString) -> BuildError {
+    BuildError::InvalidPayload {
+        message: message.to_string(),
+    }
+}
+
+fn lower_fields(fields: Vec<(&'static str, Value)>, nulls: Nulls) -> Result<Fields, BuildError> {
+    let mut assignments = Vec::new();
+
+    for (name, value) in fields {
+        if name == "id" {
+            continue;
+        }
+
+        match value {
+            Value::Null if nulls == Nulls::Skip => {}
+            value => assignments.push((name, lower(value, nulls)?)),
+        }
+    }
+
+    Ok(assignments)
+}
+
+fn lower(value: Value, nulls: Nulls) -> Result<FieldValue, BuildError> {
+    if !value.has_struct() {
+        return Ok(Either::Left(raw(value.literal())));
+    }
+
+    match value {
+        Value::Struct { name, fields } => lower_struct(name, fields, nulls),
+        Value::List(items) => {
+            let mut items = items.into_iter().map(|x| lower(x, nulls).map(expr_of));
+
+            let Some(first) = items.next().transpose()? else {
+                return Ok(Either::Left(raw("{}")));
+            };
+
+            let rest: Vec<_> = items
+                .map(|x| x.map(|x| (SetOperator::Union, x)))
+                .collect::<Result<_, _>>()?;
+
+            if rest.is_empty() {
+                return Ok(Either::Right(first));
+            }
+
+            Ok(Either::Right(ast::Expr::Set(Box::new(ast::Set {
+                first,
+                rest,
+            }))))
+        }
+        _ => Err(invalid("structs are only allowed in fields and lists")),
+    }
+}
+
+/// link to an existing object by `id`, or a new object
+fn lower_struct(
+    name: &'static str,
+    fields: Vec<(&'static str, Value)>,
+    nulls: Nulls,
+) -> Result<FieldValue, BuildError> {
+    if let Some(id) = id_literal(&fields) {
+        let select = select(name, []).filter(filter().add(AND, ".id = $?", raw(id)));
+
+        return Ok(Either::Right(select.to_ast().into_owned()));
+    }
+
+    let mut insert = insert(name);
+
+    for (field, value) in lower_fields(fields, nulls)? {
+        insert = match value {
+            Either::Left(value) => insert.set(field, value),
+            Either::Right(expr) => insert.set_expr(field, expr),
+        };
+    }
+
+    Ok(Either::Right(insert.to_ast().into_owned()))
+}
+
+fn expr_of(value: FieldValue) -> ast::Expr<'static> {
+    match value {
+        Either::Left(value) => ast::Expr::Literal(value.to_query_arg().into()),
+        Either::Right(expr) => expr,
+    }
+}
+
+/// serialized payload
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Null,
+    Str(String),
+    /// numbers, bools, enums and bytes
+    Literal(String),
+    List(Vec<Value>),
+    Tuple(Vec<Value>),
+    Struct {
+        name: &'static str,
+        fields: Vec<(&'static str, Value)>,
+    },
+}
+
+impl Value {
+    fn id(&self) -> Option<String> {
+        match self {
+            Value::Struct { fields, .. } => id_literal(fields),
+            _ => None,
+        }
+    }
+
+    /// objects with `id` are the same if ids are
+    fn same(&self, other: &Value) -> bool {
+        match (self.id(), other.id()) {
+            (Some(a), Some(b)) => a == b,
+            _ => self == other,
+        }
+    }
+
+    fn has_struct(&self) -> bool {
+        match self {
+            Value::Struct { .. } => true,
+            Value::List(x) | Value::Tuple(x) => x.iter().any(Value::has_struct),
+            _ => false,
+        }
+    }
+
+    /// value without structs
+    fn literal(&self) -> String {
+        match self {
+            Value::Null => "{}".to_string(),
+            Value::Str(x) => x.as_str().to_query_arg(),
+            Value::Literal(x) => x.clone(),
+            Value::List(x) => format!("{{ {} }}", x.iter().map(Value::literal).join(", ")),
+            Value::Tuple(x) => format!("({},)", x.iter().map(Value::literal).join(", ")),
+            Value::Struct { .. } => unreachable!("structs are lowered into statements"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PayloadError(String);
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+impl ser::Error for PayloadError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<PayloadError> for BuildError {
+    fn from(err: PayloadError) -> Self {
+        invalid(err.0)
+    }
+}
+
+fn unsupported(kind: &str) -> PayloadError {
+    PayloadError(format!("{kind} is not supported in payload"))
+}
+
+struct ValueSerializer;
+
+macro_rules! serialize_display {
+    ($($method:ident: $ty:ty $(,)?)*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Value, PayloadError> {
+                Ok(Value::Literal(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = PayloadError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = ser::Impossible<Value, PayloadError>;
+    type SerializeMap = ser::Impossible<Value, PayloadError>;
+    type SerializeStruct = StructSerializer;
+    type SerializeStructVariant = ser::Impossible<Value, PayloadError>;
+
+    serialize_display![
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+    ];
+
+    fn serialize_f32(self, v: f32) -> Result<Value, PayloadError> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, PayloadError> {
+        // `NaN`, `inf` and `-inf` are only written as strings
+        if !v.is_finite() {
+            return Ok(Value::Literal(format!("<float64>'{v}'")));
+        }
+
+        // `{:?}` keeps the fraction, e.g. `1.0`
+        Ok(Value::Literal(format!("{v:?}")))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, PayloadError> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, PayloadError> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, PayloadError> {
+        let bytes: String = v.iter().map(|x| format!("\\x{x:02x}")).collect();
+
+        Ok(Value::Literal(format!("b'{bytes}'")))
+    }
+
+    fn serialize_none(self) -> Result<Value, PayloadError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, PayloadError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, PayloadError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, PayloadError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, PayloadError> {
+        Ok(Value::Literal(format!("<{name}>'{variant}'")))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, PayloadError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, PayloadError> {
+        Err(unsupported("enum variant with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, PayloadError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or_default()),
+            tuple: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, PayloadError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len),
+            tuple: true,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, PayloadError> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, PayloadError> {
+        Err(unsupported("enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, PayloadError> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer, PayloadError> {
+        Ok(StructSerializer {
+            name,
+            fields: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, PayloadError> {
+        Err(unsupported("enum variant with data"))
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+    tuple: bool,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.items.push(value.serialize(ValueSerializer)?);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, PayloadError> {
+        if self.tuple {
+            Ok(Value::Tuple(self.items))
+        } else {
+            Ok(Value::List(self.items))
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = PayloadError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, PayloadError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = PayloadError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, PayloadError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = PayloadError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, PayloadError> {
+        self.finish()
+    }
+}
+
+struct StructSerializer {
+    name: &'static str,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl ser::SerializeStruct for StructSerializer {
+    type Ok = Value;
+    type Error = PayloadError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), PayloadError> {
+        self.fields.push((key, value.serialize(ValueSerializer)?));
+
+        Ok(())
+    }
+
+    fn skip_field(&mut self, _key: &'static str) -> Result<(), PayloadError> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, PayloadError> {
+        Ok(Value::Struct {
+            name: self.name,
+            fields: self.fields,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    enum BookKind {
+        Novel,
+    }
+
+    #[derive(Serialize)]
+    struct Author {
+        id: String,
+    }
+
+    #[derive(Serialize)]
+    struct BookTag {
+        name: String,
+    }
+
+    #[derive(Serialize)]
+    struct Book {
+        id: Option<String>,
+        title: String,
+        note: String,
+        price: f64,
+        kind: BookKind,
+        subtitle: Option<String>,
+        author: Author,
+        book_tags: Vec<BookTag>,
+    }
+
+    #[derive(Clone, Serialize)]
+    struct Tag {
+        id: String,
+        name: String,
+    }
+
+    #[derive(Clone, Serialize)]
+    struct Cover {
+        path: String,
+    }
+
+    #[derive(Clone, Serialize)]
+    #[serde(rename = "Book")]
+    struct Saved {
+        id: String,
+        title: String,
+        subtitle: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        price: i64,
+        tags: Vec<Tag>,
+        cover: Cover,
+    }
+
+    #[derive(Serialize)]
+    struct Writer {
+        name: String,
+        born: String,
+    }
+
+    #[derive(Serialize)]
+    struct Release {
+        isbn: String,
+        published: String,
+        writer: Writer,
+    }
+
+    #[derive(Serialize)]
+    struct Rating {
+        average: f64,
+        low: f32,
+        high: f64,
+    }
+
+    #[test]
+    fn print() {
+        let book = Book {
+            id: None,
+            title: "it's".to_string(),
+            note: "C:\\".to_string(),
+            price: 10.0,
+            kind: BookKind::Novel,
+            subtitle: None,
+            author: Author {
+                id: "2ab4a8c0-6b54-11ee-b962-0242ac120002".to_string(),
+            },
+            book_tags: vec![
+                BookTag {
+                    name: "a".to_string(),
+                },
+                BookTag {
+                    name: "b".to_string(),
+                },
+            ],
+        };
+
+        let query = insert_struct(&book).unwrap().to_query();
+
+        assert_eq!(
+            query,
+            "\
+insert Book {
+  title := <str>'it\\'s',
+  note := <str>'C:\\\\',
+  price := 10.0,
+  kind := <BookKind>'Novel',
+  author := (
+    select Author
+    filter
+      .id = <uuid><str>'2ab4a8c0-6b54-11ee-b962-0242ac120002'
+  ),
+  book_tags := (
+    (
+      insert BookTag {
+        name := <str>'a',
+      }
+    ) union (
+      insert BookTag {
+        name := <str>'b',
+      }
+    )
+  ),
+}"
+        );
+
+        let query = update("Book")
+            .filter(filter().add(AND, ".title = $?", "it's"))
+            .set_struct(&book, Nulls::Empty)
+            .unwrap()
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+filter
+  .title = <str>'it\\'s'
+set {
+  title := <str>'it\\'s',
+  note := <str>'C:\\\\',
+  price := 10.0,
+  kind := <BookKind>'Novel',
+  subtitle := {},
+  author := (
+    select Author
+    filter
+      .id = <uuid><str>'2ab4a8c0-6b54-11ee-b962-0242ac120002'
+  ),
+  book_tags := (
+    (
+      insert BookTag {
+        name := <str>'a',
+      }
+    ) union (
+      insert BookTag {
+        name := <str>'b',
+      }
+    )
+  ),
+}"
+        );
+
+        assert_eq!(
+            insert_struct(&1).err(),
+            Some(BuildError::InvalidPayload {
+                message: "payload must be a struct".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn non_finite() {
+        let rating = Rating {
+            average: f64::NAN,
+            low: f32::NEG_INFINITY,
+            high: f64::INFINITY,
+        };
+
+        let query = insert_struct(&rating).unwrap().to_query();
+
+        assert_eq!(
+            query,
+            "\
+insert Rating {
+  average := <float64>'NaN',
+  low := <float64>'-inf',
+  high := <float64>'inf',
+}"
+        );
+    }
+
+    #[test]
+    fn cast() {
+        let release = Release {
+            isbn: "2ab4a8c0-6b54-11ee-b962-0242ac120002".to_string(),
+            published: "2023-10-16T00:00:00+00".to_string(),
+            writer: Writer {
+                name: "kim".to_string(),
+                born: "1970-01-01T00:00:00+00".to_string(),
+            },
+        };
+
+        let query = insert_struct(&release)
+            .unwrap()
+            .cast("isbn", "uuid")
+            .cast("published", "datetime")
+            .cast("writer.born", "datetime")
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+insert Release {
+  isbn := <uuid><str>'2ab4a8c0-6b54-11ee-b962-0242ac120002',
+  published := <datetime><str>'2023-10-16T00:00:00+00',
+  writer := (
+    insert Writer {
+      name := <str>'kim',
+      born := <datetime><str>'1970-01-01T00:00:00+00',
+    }
+  ),
+}"
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let tag = |id: &str| Tag {
+            id: id.to_string(),
+            name: id.to_string(),
+        };
+
+        let original = Saved {
+            id: "2ab4a8c0-6b54-11ee-b962-0242ac120002".to_string(),
+            title: "a".to_string(),
+            subtitle: Some("b".to_string()),
+            note: Some("c".to_string()),
+            price: 100,
+            tags: vec![tag("1"), tag("2")],
+            cover: Cover {
+                path: "a.png".to_string(),
+            },
+        };
+
+        let mut modified = original.clone();
+        modified.subtitle = None;
+        modified.note = None;
+        modified.price = 200;
+        modified.tags = vec![tag("2"), tag("3")];
+
+        let query = update_diff(&original, &modified).unwrap().to_query();
+
+        assert_eq!(
+            query,
+            "\
+update Book
+filter
+  .id = <uuid><str>'2ab4a8c0-6b54-11ee-b962-0242ac120002'
+set {
+  note := {},
+  subtitle := {},
+  price := 200,
+  tags += (
+    select Tag
+    filter
+      .id = <uuid><str>'3'
+  ),
+  tags -= (
+    select Tag
+    filter
+      .id = <uuid><str>'1'
+  ),
+}"
+        );
+
+        assert!(update_diff(&original, &original).unwrap().is_empty());
+
+        let mut renamed = original.clone();
+        renamed.tags[0].name = "x".to_string();
+
+        assert_eq!(
+            update_diff(&original, &renamed).err(),
+            Some(BuildError::InvalidPayload {
+                message: "`tags` changes fields of a linked object".to_string()
+            })
+        );
+
+        let mut recovered = original.clone();
+        recovered.cover.path = "b.png".to_string();
+
+        assert_eq!(
+            update_diff(&original, &recovered).err(),
+            Some(BuildError::InvalidPayload {
+                message: "`cover` replaces an object without id".to_string()
+            })
+        );
+
+        let rating = Rating {
+            average: 4.5,
+            low: 1.0,
+            high: 5.0,
+        };
+
+        assert_eq!(
+            update_diff(&rating, &rating).err(),
+            Some(BuildError::InvalidPayload {
+                message: "original has no id".to_string()
+            })
+        );
+    }
+}
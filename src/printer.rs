@@ -0,0 +1,888 @@
+//! renders `ast::Expr` into EdgeQL
+//!
+//! every node is written from the current position, and its following lines are indented by `indent`.
+//! in compact layout line breaks become a space and indentation is omitted.
+//! in redacted output literals are written as `$?`.
+
+use std::fmt;
+
+use edgedb_protocol::value::Value;
+use either::Either;
+
+use crate::ast::*;
+use crate::{SetOperator, ARG_IDENTITY};
+
+pub(crate) fn write(
+    w: &mut dyn fmt::Write,
+    expr: &Expr,
+    indent: usize,
+    compact: bool,
+) -> fmt::Result {
+    let mut printer = Printer::new(w, compact);
+
+    printer.pad(indent);
+    printer.write_expr(expr, indent);
+
+    printer.result
+}
+
+pub(crate) fn print(expr: &Expr, indent: usize) -> String {
+    let mut q = String::new();
+
+    // writing into `String` never fails
+    let _ = write(&mut q, expr, indent, false);
+
+    q
+}
+
+/// one line without indentation
+pub(crate) fn print_compact(expr: &Expr) -> String {
+    let mut q = String::new();
+
+    let _ = write(&mut q, expr, 0, true);
+
+    q
+}
+
+/// arguments replaced by `$?`, for logs and errors
+pub(crate) fn print_redacted(expr: &Expr) -> String {
+    let mut q = String::new();
+
+    let mut printer = Printer::new(&mut q, false);
+    printer.redact = true;
+    printer.write_expr(expr, 0);
+
+    q
+}
+
+const SPACES: &str = "                                ";
+
+/// whether `expr` is written over several lines, decided from the tree without rendering it
+fn is_multiline(expr: &Expr<'_>) -> bool {
+    match expr {
+        Expr::Raw(x) | Expr::Literal(x) => x.contains('\n'),
+        Expr::Param(_) => false,
+        Expr::Rendered(x) => x.text.pretty.iter().any(|x| x.contains('\n')),
+        Expr::Seq(x) | Expr::Coalesce(x) => x.iter().any(is_multiline),
+        Expr::Select(x) => {
+            !x.withs.is_empty()
+                || !x.shape.is_empty()
+                || x.filter.is_some()
+                || !x.orders.is_empty()
+                || x.offset.is_some()
+                || x.limit.is_some()
+                || is_multiline(&x.subject)
+        }
+        Expr::Delete(x) => {
+            !x.withs.is_empty()
+                || x.subject.contains('\n')
+                || x.filter.is_some()
+                || !x.orders.is_empty()
+                || x.offset.is_some()
+                || x.limit.is_some()
+        }
+        Expr::Condition(x) => is_multiline_condition(x),
+        Expr::IfElse(x) => !x.branches.is_empty() || x.otherwise.as_ref().is_some_and(is_multiline),
+        Expr::Call(x) => x.args.iter().any(|(_, arg)| is_multiline(arg)),
+        Expr::ShapeElement(x) => match &**x {
+            ShapeElement::Splat | ShapeElement::DoubleSplat => false,
+            ShapeElement::Field { shape, .. } => !shape.is_empty(),
+            ShapeElement::Computed { .. } => true,
+        },
+        Expr::WithItem(x) => match &**x {
+            WithItem::Binding { value, .. } => is_multiline(value),
+            WithItem::Module { .. } => false,
+        },
+        Expr::UnlessConflict(x) => x.on.is_some() && x.else_expr.is_some(),
+        // brackets on their own lines, or clauses on their own lines
+        Expr::Paren(_)
+        | Expr::Prefix(..)
+        | Expr::Set(_)
+        | Expr::Insert(_)
+        | Expr::Update(_)
+        | Expr::Group(_)
+        | Expr::For(_) => true,
+    }
+}
+
+fn is_multiline_condition(cond: &Condition<'_>) -> bool {
+    cond.is_parenthesized()
+        || cond.terms.len() > 1
+        || cond.terms.iter().any(|(_, term)| match term {
+            Term::Raw(x) => x.contains('\n'),
+            Term::Arg { raw, arg } => {
+                raw.contains('\n') || (raw.contains(ARG_IDENTITY) && is_multiline(arg))
+            }
+            Term::Expr(x) => is_multiline(x),
+            // `(cond)` on its own lines
+            Term::Group(_) => true,
+        })
+}
+
+/// writes into one sink. the first error is kept and later writes are skipped
+struct Printer<'w> {
+    w: &'w mut dyn fmt::Write,
+    compact: bool,
+    redact: bool,
+    result: fmt::Result,
+}
+
+impl<'w> Printer<'w> {
+    fn new(w: &'w mut dyn fmt::Write, compact: bool) -> Self {
+        Self {
+            w,
+            compact,
+            redact: false,
+            result: Ok(()),
+        }
+    }
+
+    fn push(&mut self, char: char) {
+        if self.result.is_ok() {
+            self.result = self.w.write_char(char);
+        }
+    }
+
+    fn push_str(&mut self, string: &str) {
+        if self.result.is_ok() {
+            self.result = self.w.write_str(string);
+        }
+    }
+
+    fn push_display(&mut self, x: impl fmt::Display) {
+        if self.result.is_ok() {
+            self.result = write!(self.w, "{x}");
+        }
+    }
+
+    /// raw EdgeQL. in compact layout whitespace outside of string literals becomes one space
+    fn push_raw(&mut self, raw: &str) {
+        if !self.compact {
+            self.push_str(raw);
+
+            return;
+        }
+
+        let mut quote = None;
+        let mut escaped = false;
+        let mut space = false;
+
+        for c in raw.chars() {
+            match quote {
+                Some(_) if escaped => escaped = false,
+                Some(_) if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c.is_whitespace() => {
+                    space = true;
+
+                    continue;
+                }
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None => {}
+            }
+
+            if std::mem::take(&mut space) {
+                self.push(' ');
+            }
+
+            self.push(c);
+        }
+
+        if space {
+            self.push(' ');
+        }
+    }
+
+    fn pad(&mut self, indent: usize) {
+        if self.compact {
+            return;
+        }
+
+        let mut indent = indent;
+
+        while indent > 0 {
+            let n = indent.min(SPACES.len());
+
+            self.push_str(&SPACES[..n]);
+            indent -= n;
+        }
+    }
+
+    /// line break between clauses or items
+    fn line(&mut self, indent: usize) {
+        if self.compact {
+            self.push(' ');
+        } else {
+            self.push('\n');
+            self.pad(indent);
+        }
+    }
+
+    /// line break right inside or before a bracket
+    fn wrap(&mut self, indent: usize) {
+        if !self.compact {
+            self.push('\n');
+            self.pad(indent);
+        }
+    }
+
+    /// trailing comma is omitted in compact layout
+    fn comma(&mut self, last: bool) {
+        if !(self.compact && last) {
+            self.push(',');
+        }
+    }
+
+    fn write_expr(&mut self, expr: &Expr, indent: usize) {
+        match expr {
+            Expr::Raw(x) => self.push_raw(x),
+            Expr::Literal(_) if self.redact => self.push_str(ARG_IDENTITY),
+            Expr::Literal(x) => self.push_str(x),
+            Expr::Param(x) => self.write_param(x),
+            Expr::Rendered(x) => self.write_rendered(x, indent),
+            Expr::Seq(x) => x.iter().for_each(|x| self.write_expr(x, indent)),
+            Expr::Paren(x) => self.write_paren(x, indent),
+            Expr::Select(x) => self.write_select(x, indent),
+            Expr::Insert(x) => self.write_insert(x, indent),
+            Expr::Update(x) => self.write_update(x, indent),
+            Expr::Delete(x) => self.write_delete(x, indent),
+            Expr::Group(x) => self.write_group(x, indent),
+            Expr::For(x) => self.write_for(x, indent),
+            Expr::Condition(x) => self.write_condition(x, indent),
+            Expr::IfElse(x) => self.write_if_else(x, indent),
+            Expr::Coalesce(x) => {
+                for (i, operand) in x.iter().enumerate() {
+                    if i > 0 {
+                        self.push_str(" ?? ");
+                    }
+
+                    self.write_expr(operand, indent);
+                }
+            }
+            Expr::Prefix(op, x) => {
+                self.push_str(op);
+                self.push(' ');
+
+                self.write_paren(x, indent);
+            }
+            Expr::Call(x) => self.write_call(x, indent),
+            Expr::Set(x) => self.write_set_operands(x, x.rest.len(), indent),
+            Expr::ShapeElement(x) => {
+                self.write_shape_element(x, indent);
+                self.push(',');
+            }
+            Expr::WithItem(x) => self.write_with_item(x, indent),
+            Expr::UnlessConflict(x) => self.write_unless_conflict(x, indent),
+        }
+    }
+
+    fn write_paren(&mut self, expr: &Expr, indent: usize) {
+        self.push('(');
+        self.wrap(2 + indent);
+
+        self.write_expr(expr, 2 + indent);
+
+        self.wrap(indent);
+        self.push(')');
+    }
+
+    fn write_withs(&mut self, withs: &[WithItem], indent: usize) {
+        if withs.is_empty() {
+            return;
+        }
+
+        self.push_str("with");
+
+        for (i, with) in withs.iter().enumerate() {
+            self.line(2 + indent);
+            self.write_with_item(with, 2 + indent);
+            self.comma(i + 1 == withs.len());
+        }
+
+        self.line(indent);
+    }
+
+    fn write_with_item(&mut self, with: &WithItem, indent: usize) {
+        match with {
+            WithItem::Binding { name, value } => {
+                self.push_str(name);
+                self.push_str(" := ");
+
+                self.write_expr(value, indent);
+            }
+            WithItem::Module { alias, module } => {
+                if let Some(alias) = alias {
+                    self.push_str(alias);
+                    self.push_str(" as ");
+                }
+
+                self.push_str("module ");
+                self.push_str(module);
+            }
+        }
+    }
+
+    fn write_shape(&mut self, shape: &[ShapeElement], indent: usize) {
+        self.push('{');
+
+        for (i, element) in shape.iter().enumerate() {
+            if i == 0 {
+                self.wrap(2 + indent);
+            } else {
+                self.line(2 + indent);
+            }
+
+            self.write_shape_element(element, 2 + indent);
+            self.comma(i + 1 == shape.len());
+        }
+
+        self.wrap(indent);
+        self.push('}');
+    }
+
+    fn write_shape_element(&mut self, element: &ShapeElement, indent: usize) {
+        match element {
+            ShapeElement::Splat => self.push('*'),
+            ShapeElement::DoubleSplat => self.push_str("**"),
+            ShapeElement::Field { name, shape } => {
+                self.push_str(name);
+
+                if !shape.is_empty() {
+                    self.push(':');
+                    self.push(' ');
+
+                    self.write_shape(shape, indent);
+                }
+            }
+            ShapeElement::Computed { name, expr } => {
+                self.push_str(name);
+                self.push_str(" := ");
+
+                self.write_paren(expr, indent);
+            }
+        }
+    }
+
+    fn write_filter(&mut self, filter: Option<&Condition>, indent: usize) {
+        if let Some(filter) = filter {
+            self.line(indent);
+            self.push_str("filter");
+
+            self.line(2 + indent);
+            self.write_condition(filter, 2 + indent);
+        }
+    }
+
+    fn write_orders(&mut self, orders: &[Order], indent: usize) {
+        for (i, order) in orders.iter().enumerate() {
+            if i == 0 {
+                self.line(indent);
+                self.push_str("order by");
+            } else {
+                self.push(' ');
+                self.push_str("then");
+            }
+
+            self.line(2 + indent);
+            self.write_expr(&order.by, 2 + indent);
+
+            if let Some(direction) = order.direction {
+                self.push(' ');
+                self.push_str(direction.as_str());
+            }
+        }
+    }
+
+    fn write_offset_limit(&mut self, offset: Option<usize>, limit: Option<usize>, indent: usize) {
+        if let Some(offset) = offset {
+            self.line(indent);
+            self.push_str("offset ");
+            self.push_display(offset);
+        }
+
+        if let Some(limit) = limit {
+            self.line(indent);
+            self.push_str("limit ");
+            self.push_display(limit);
+        }
+    }
+
+    fn write_assignments(&mut self, assignments: &[Assignment], indent: usize) {
+        self.push('{');
+
+        for (i, assignment) in assignments.iter().enumerate() {
+            if i == 0 {
+                self.wrap(2 + indent);
+            } else {
+                self.line(2 + indent);
+            }
+
+            self.push_str(&assignment.name);
+
+            self.push(' ');
+            self.push_str(assignment.assign.as_str());
+            self.push(' ');
+
+            self.write_expr(&assignment.value, 2 + indent);
+            self.comma(i + 1 == assignments.len());
+        }
+
+        self.wrap(indent);
+        self.push('}');
+    }
+
+    fn write_select(&mut self, select: &Select, indent: usize) {
+        self.write_withs(&select.withs, indent);
+
+        self.push_str("select ");
+
+        if select.distinct {
+            self.push_str("distinct ");
+        }
+
+        self.write_expr(&select.subject, indent);
+
+        if !select.shape.is_empty() {
+            self.push(' ');
+            self.write_shape(&select.shape, indent);
+        }
+
+        self.write_filter(select.filter.as_ref(), indent);
+        self.write_orders(&select.orders, indent);
+        self.write_offset_limit(select.offset, select.limit, indent);
+    }
+
+    fn write_insert(&mut self, insert: &Insert, indent: usize) {
+        self.write_withs(&insert.withs, indent);
+
+        self.push_str("insert ");
+        self.push_str(&insert.subject);
+        self.push(' ');
+
+        self.write_assignments(&insert.assignments, indent);
+
+        if let Some(unless_conflict) = &insert.unless_conflict {
+            self.line(indent);
+
+            self.write_unless_conflict(unless_conflict, indent);
+        }
+    }
+
+    fn write_unless_conflict(&mut self, unless_conflict: &UnlessConflict, indent: usize) {
+        self.push_str("unless conflict");
+
+        if let Some(on) = &unless_conflict.on {
+            self.push_str(" on ");
+            self.push_str(on);
+
+            if let Some(else_expr) = &unless_conflict.else_expr {
+                self.line(indent);
+                self.push_str("else ");
+
+                self.write_paren(else_expr, indent);
+            }
+        }
+    }
+
+    fn write_update(&mut self, update: &Update, indent: usize) {
+        self.write_withs(&update.withs, indent);
+
+        self.push_str("update ");
+        self.write_expr(&update.subject, indent);
+
+        self.write_filter(update.filter.as_ref(), indent);
+
+        self.line(indent);
+        self.push_str("set ");
+
+        self.write_assignments(&update.assignments, indent);
+    }
+
+    fn write_delete(&mut self, delete: &Delete, indent: usize) {
+        self.write_withs(&delete.withs, indent);
+
+        self.push_str("delete ");
+        self.push_str(&delete.subject);
+
+        self.write_filter(delete.filter.as_ref(), indent);
+        self.write_orders(&delete.orders, indent);
+        self.write_offset_limit(delete.offset, delete.limit, indent);
+    }
+
+    fn write_group(&mut self, group: &Group, indent: usize) {
+        self.write_withs(&group.withs, indent);
+
+        self.push_str("group ");
+        self.push_str(&group.subject);
+
+        if let Some(shape) = &group.shape {
+            self.push_str(" { ");
+            self.push_str(shape);
+            self.push_str(" }");
+        }
+
+        if !group.using.is_empty() {
+            self.line(indent);
+            self.push_str("using");
+
+            self.line(2 + indent);
+            self.push_str(&group.using.join(", "));
+        }
+
+        self.line(indent);
+        self.push_str("by");
+
+        self.line(2 + indent);
+        self.push_str(&group.by.join(", "));
+    }
+
+    fn write_for(&mut self, for_in: &For, indent: usize) {
+        self.write_withs(&for_in.withs, indent);
+
+        self.push_str("for ");
+        self.push_str(&for_in.binding);
+        self.push_str(" in ");
+
+        self.write_expr(&for_in.set, indent);
+
+        self.push_str(" union ");
+
+        self.write_paren(&for_in.body, indent);
+    }
+
+    fn write_condition(&mut self, cond: &Condition, indent: usize) {
+        if cond.not {
+            self.push_str("not ");
+        }
+
+        if let Some(func) = &cond.func {
+            self.push_str(func);
+        }
+
+        let inner = if cond.is_parenthesized() {
+            self.push('(');
+            self.wrap(2 + indent);
+
+            2 + indent
+        } else {
+            indent
+        };
+
+        for (i, (and_or, term)) in cond.terms.iter().enumerate() {
+            if i > 0 {
+                self.push(' ');
+                self.push_str(and_or.as_str());
+                self.line(inner);
+            }
+
+            match term {
+                Term::Raw(x) => self.push_raw(x),
+                Term::Arg { raw, arg } => match raw.split_once(ARG_IDENTITY) {
+                    Some((before, after)) => {
+                        self.push_raw(before);
+                        self.write_expr(arg, inner);
+                        self.push_raw(after);
+                    }
+                    None => self.push_raw(raw),
+                },
+                Term::Expr(expr) => self.write_expr(expr, inner),
+                Term::Group(x) if x.is_parenthesized() => self.write_condition(x, inner),
+                Term::Group(x) => self.write_if_condition(x, inner),
+            }
+        }
+
+        if cond.is_parenthesized() {
+            self.wrap(indent);
+            self.push(')');
+        }
+    }
+
+    /// `(cond)`
+    fn write_if_condition(&mut self, cond: &Condition, indent: usize) {
+        self.push('(');
+        self.wrap(2 + indent);
+
+        self.write_condition(cond, 2 + indent);
+
+        self.wrap(indent);
+        self.push(')');
+    }
+
+    fn write_if_else(&mut self, if_else: &IfElse, indent: usize) {
+        if if_else.statement {
+            for (i, (cond, then)) in if_else.branches.iter().enumerate() {
+                if i > 0 {
+                    self.push_str(" else ");
+                }
+
+                self.push_str("if ");
+                self.write_if_condition(cond, indent);
+
+                self.push_str(" then ");
+                self.write_expr(then, indent);
+            }
+
+            if let Some(otherwise) = &if_else.otherwise {
+                self.push_str(" else ");
+                self.write_expr(otherwise, indent);
+            }
+        } else {
+            for (cond, then) in &if_else.branches {
+                self.write_expr(then, indent);
+
+                self.push_str(" if ");
+                self.write_if_condition(cond, indent);
+
+                self.push_str(" else ");
+            }
+
+            match &if_else.otherwise {
+                Some(otherwise) => self.write_expr(otherwise, indent),
+                // empty set
+                None => self.push_str("{}"),
+            }
+        }
+    }
+
+    fn write_param(&mut self, param: &Param) {
+        // a `Vec` is a set where it is written as literal
+        let unpack = matches!(param.value, Value::Array(_));
+
+        if unpack {
+            self.push_str("array_unpack(");
+        }
+
+        self.push('<');
+        self.push_str(&param.ty);
+        self.push_str(">$");
+        self.push_str(&param.name);
+
+        if unpack {
+            self.push(')');
+        }
+    }
+
+    /// text rendered at indent 0, so its following lines are padded by `indent`
+    fn write_rendered(&mut self, rendered: &Rendered, indent: usize) {
+        let text = if self.redact {
+            &rendered.text.redacted
+        } else if self.compact {
+            &rendered.text.compact
+        } else {
+            &rendered.text.pretty
+        };
+
+        for (i, part) in text.iter().enumerate() {
+            for (j, line) in part.split('\n').enumerate() {
+                if j > 0 {
+                    self.wrap(indent);
+                }
+
+                self.push_str(line);
+            }
+
+            match rendered.slots.get(i) {
+                Some(Either::Right(param)) => self.write_param(param),
+                Some(Either::Left(name)) => {
+                    self.push('$');
+                    self.push_str(name);
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn write_call(&mut self, call: &Call, indent: usize) {
+        let multiline = if self.compact {
+            Vec::new()
+        } else {
+            call.args.iter().map(|(_, arg)| is_multiline(arg)).collect()
+        };
+
+        self.push_str(&call.name);
+        self.push('(');
+
+        // `name(a, b := c)` if every argument fits in a line
+        if !multiline.contains(&true) {
+            for (i, (name, arg)) in call.args.iter().enumerate() {
+                if i > 0 {
+                    self.push_str(", ");
+                }
+
+                if let Some(name) = name {
+                    self.push_str(name);
+                    self.push_str(" := ");
+                }
+
+                self.write_expr(arg, indent);
+            }
+
+            self.push(')');
+
+            return;
+        }
+
+        for (i, ((name, arg), multiline)) in call.args.iter().zip(multiline).enumerate() {
+            if i > 0 {
+                self.push(',');
+            }
+
+            self.line(2 + indent);
+
+            if let Some(name) = name {
+                self.push_str(name);
+                self.push_str(" := ");
+            }
+
+            // a call closes its own parentheses
+            match arg {
+                Expr::Paren(_) | Expr::Call(_) => self.write_expr(arg, 2 + indent),
+                _ if multiline => self.write_paren(arg, 2 + indent),
+                _ => self.write_expr(arg, 2 + indent),
+            }
+        }
+
+        self.wrap(indent);
+        self.push(')');
+    }
+
+    /// renders `first` and `rest[..n]`
+    fn write_set_operands(&mut self, set: &Set, n: usize, indent: usize) {
+        if n == 0 {
+            self.write_paren(&set.first, indent);
+
+            return;
+        }
+
+        let (op, x) = &set.rest[n - 1];
+
+        // intersect binds tighter than union and except
+        let wrap = *op == SetOperator::Intersect
+            && set.rest[..n - 1]
+                .iter()
+                .any(|(op, _)| *op != SetOperator::Intersect);
+
+        if wrap {
+            self.push('(');
+            self.wrap(2 + indent);
+
+            self.write_set_operands(set, n - 1, 2 + indent);
+
+            self.wrap(indent);
+            self.push(')');
+        } else {
+            self.write_set_operands(set, n - 1, indent);
+        }
+
+        self.push(' ');
+        self.push_str(op.as_str());
+        self.push(' ');
+
+        self.write_paren(x, indent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn compact() {
+        let query = select("Book", crate::fields! { title, author: { name } })
+            .with(with("min_price", 100))
+            .filter(
+                filter()
+                    .add(AND, ".price > $?", raw("min_price"))
+                    .add_filter(OR, !filter().add(AND, ".title = $?", "a b")),
+            )
+            .order_by(order_by(".title", ASC))
+            .take(10);
+
+        let compact = query.to_query_compact();
+
+        assert_eq!(
+            compact,
+            "with min_price := 100 select Book {title, author: {name}} filter .price > min_price or not (.title = <str>'a b') order by .title asc limit 10"
+        );
+
+        let query = select("Book", crate::fields! { title })
+            .filter(filter().add(AND, ".title = 'a  b'\n  and\n .price > $?", 100))
+            .to_query_compact();
+
+        assert_eq!(
+            query,
+            "select Book {title} filter .title = 'a  b' and .price > 100"
+        );
+
+        let query = on_conflict()
+            .field(".isbn")
+            .else_expr(select("Book", []))
+            .to_query_compact();
+
+        assert_eq!(query, "unless conflict on .isbn else (select Book)");
+    }
+
+    #[test]
+    fn display() {
+        let query = update("Book")
+            .filter(filter().add(AND, ".uid = $?", 1234))
+            .set_expr("tags", Assign::Add, select("BookTag", []).take(2));
+
+        let mut q = String::new();
+
+        query.write_query(&mut q).unwrap();
+
+        assert_eq!(q, query.to_query());
+        assert_eq!(query.to_string(), query.to_query());
+    }
+
+    #[test]
+    fn nested_call() {
+        let inline = count(len(raw(".title")));
+        let nested = count(array_agg(select("Book", []).take(2)));
+
+        let inline = inline.to_query();
+        let nested = nested.to_query();
+
+        assert_eq!(inline, "count(len(.title))");
+        assert_eq!(
+            nested,
+            "\
+count(
+  array_agg(
+    (
+      select Book
+      limit 2
+    )
+  )
+)"
+        );
+    }
+
+    #[test]
+    fn redacted() {
+        let query = select("Book", crate::fields! { title })
+            .with(with("secret", "token"))
+            .filter(
+                filter()
+                    .add(AND, ".uid = $?", 1234)
+                    .add(AND, ".title = $?", "private"),
+            );
+
+        let redacted = super::print_redacted(&query.to_ast());
+
+        assert_eq!(
+            redacted,
+            "\
+with
+  secret := $?,
+select Book {
+  title,
+}
+filter
+  .uid = $? and
+  .title = $?"
+        );
+    }
+}
@@ -1,49 +1,94 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, marker::PhantomData};
 
 use super::*;
 
-#[derive(Clone)]
-pub struct SelectBuilder<'a, T: 'a> {
+/// `Out` is the result type inferred by `query`, `query_single` and `query_required_single`
+///
+/// ```ignore
+/// let books: Vec<Book> = select_as::<Book>(fields! { title }).query(&client).await?;
+/// ```
+pub struct SelectBuilder<'a, Out = Untyped> {
     target: Cow<'a, str>,
-    fields: T,
+    fields: Arc<[Field<'a>]>,
     withs: Vec<With<'a>>,
+    module: Option<Cow<'a, str>>,
     filter: Option<Filter<'a>>,
+    soft_delete: Option<SoftDelete<'a>>,
+    with_deleted: bool,
     skip: Option<usize>,
     take: Option<usize>,
     orders: Vec<OrderBy<'a>>,
     distinct: bool,
-    expr: Option<Box<dyn ToQuery + 'a>>,
+    expr: Option<Arc<dyn ToQuery + 'a>>,
+    out: PhantomData<fn() -> Out>,
 }
 
-pub fn select<'a, T>(target: impl Into<Cow<'a, str>>, fields: T) -> SelectBuilder<'a, T>
+pub fn select<'a>(
+    target: impl Into<Cow<'a, str>>,
+    fields: impl IntoIterator<Item = Field<'a>>,
+) -> SelectBuilder<'a> {
+    SelectBuilder::new(target, fields)
+}
+
+/// target is `T::type_name()`. soft-deleted objects are excluded by `T::soft_delete()`
+pub fn select_as<'a, T>(fields: impl IntoIterator<Item = Field<'a>>) -> SelectBuilder<'a, T>
 where
-    T: IntoIterator<Item = Field<'a>>,
+    T: TypeName,
 {
-    SelectBuilder::new(target, fields)
+    let mut select = SelectBuilder::new(T::type_name(), fields);
+
+    select.soft_delete = T::soft_delete();
+
+    select.returns()
 }
 
-pub fn select_expr<'a, T>(expr: T) -> SelectBuilder<'a, [Field<'a>; 0]>
+pub fn select_expr<'a, T>(expr: T) -> SelectBuilder<'a>
 where
     T: ToQuery + 'a,
 {
     SelectBuilder::new("", []).expr(expr)
 }
 
-impl<'a, T> SelectBuilder<'a, T>
-where
-    T: IntoIterator<Item = Field<'a>>,
-{
-    pub fn new(target: impl Into<Cow<'a, str>>, fields: T) -> Self {
+impl<'a> SelectBuilder<'a> {
+    pub fn new(
+        target: impl Into<Cow<'a, str>>,
+        fields: impl IntoIterator<Item = Field<'a>>,
+    ) -> Self {
         Self {
             target: target.into(),
-            fields,
+            fields: fields.into_iter().collect(),
             withs: Vec::new(),
+            module: None,
             filter: None,
+            soft_delete: None,
+            with_deleted: false,
             skip: None,
             take: None,
             orders: Vec::new(),
             distinct: false,
             expr: None,
+            out: PhantomData,
+        }
+    }
+}
+
+impl<'a, Out> SelectBuilder<'a, Out> {
+    /// result type of `query`, `query_single` and `query_required_single`
+    pub fn returns<T>(self) -> SelectBuilder<'a, T> {
+        SelectBuilder {
+            target: self.target,
+            fields: self.fields,
+            withs: self.withs,
+            module: self.module,
+            filter: self.filter,
+            soft_delete: self.soft_delete,
+            with_deleted: self.with_deleted,
+            skip: self.skip,
+            take: self.take,
+            orders: self.orders,
+            distinct: self.distinct,
+            expr: self.expr,
+            out: PhantomData,
         }
     }
 
@@ -53,6 +98,13 @@ where
         self
     }
 
+    /// default module. `with module module`
+    pub fn module(mut self, module: impl Into<Cow<'a, str>>) -> Self {
+        self.module.replace(module.into());
+
+        self
+    }
+
     pub fn distinct(mut self, distinct: bool) -> Self {
         self.distinct = distinct;
 
@@ -63,7 +115,7 @@ where
     where
         U: ToQuery + 'a,
     {
-        self.expr.replace(Box::new(expr));
+        self.expr.replace(Arc::new(expr));
 
         self
     }
@@ -74,6 +126,13 @@ where
         self
     }
 
+    /// include soft-deleted objects
+    pub fn with_deleted(mut self) -> Self {
+        self.with_deleted = true;
+
+        self
+    }
+
     pub fn skip(mut self, n: usize) -> Self {
         self.skip.replace(n);
 
@@ -91,106 +150,70 @@ where
 
         self
     }
-}
-
-impl<'a, T> ToQuery for SelectBuilder<'a, T>
-where
-    T: IntoIterator<Item = Field<'a>> + Send + Sync + Clone + 'a,
-{
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let fields = self.fields.clone().into_iter();
 
-        let mut qx = String::new();
-        let q = &mut qx;
+    fn condition(&self) -> Option<ast::Condition<'_>> {
+        let policy = self.soft_delete.as_ref().filter(|_| !self.with_deleted);
 
-        // with
-        {
-            push_withs(q, &self.withs, indent);
-        }
-
-        // select
-        {
-            push_str(q, "select", indent);
-            q.push(' ');
-
-            if self.distinct {
-                q.push_str("distinct");
-                q.push(' ');
-            }
-
-            q.push_str(self.target.as_ref());
-
-            if fields.peekable().count() > 0 {
-                q.push(' ');
-                q.push('{');
-                q.push('\n');
-
-                push_fields(q, self.fields.clone(), 2 + indent);
-
-                push_str(q, "}", indent);
-            }
-        }
-
-        // expr
-        {
-            if let Some(expr) = &self.expr {
-                q.push(' ');
-                // q.push('(');
-                q.push('\n');
-
-                q.push_str(&expr.to_query_with_indent(2 + indent));
-
-                q.push('\n');
-                push(q, ')', indent);
-            }
-        }
-
-        // filter
-        {
-            if self.filter.is_some() {
-                q.push('\n');
-            }
+        soft_deleted_condition(self.filter.as_ref(), policy)
+    }
+}
 
-            push_filter(q, self.filter.as_ref(), indent);
+// derive would require `Out: Clone`
+impl<'a, Out> Clone for SelectBuilder<'a, Out> {
+    fn clone(&self) -> Self {
+        Self {
+            target: self.target.clone(),
+            fields: self.fields.clone(),
+            withs: self.withs.clone(),
+            module: self.module.clone(),
+            filter: self.filter.clone(),
+            soft_delete: self.soft_delete.clone(),
+            with_deleted: self.with_deleted,
+            skip: self.skip,
+            take: self.take,
+            orders: self.orders.clone(),
+            distinct: self.distinct,
+            expr: self.expr.clone(),
+            out: PhantomData,
         }
+    }
+}
 
-        // order by
-        {
-            let mut orders = self.orders.iter();
-
-            if let Some(first) = orders.next() {
-                q.push('\n');
-                push_str(q, "order by", indent);
-                q.push('\n');
-                q.push_str(&first.to_query_with_indent(indent));
-            }
-
-            for ord in orders {
-                q.push(' ');
-                q.push_str("then");
-                q.push('\n');
-
-                q.push_str(&ord.to_query_with_indent(indent));
-            }
-        }
+impl<'a, Out> ToQuery for SelectBuilder<'a, Out> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let module = self.module.as_deref().map(|module| ast::WithItem::Module {
+            alias: None,
+            module: module.into(),
+        });
+
+        let subject = match &self.expr {
+            Some(expr) => ast::Expr::Paren(Box::new(expr.to_ast())),
+            None => ast::Expr::Raw(self.target.as_ref().into()),
+        };
+
+        ast::Expr::Select(Box::new(ast::Select {
+            withs: module
+                .into_iter()
+                .chain(self.withs.iter().map(With::to_with_item))
+                .collect(),
+            distinct: self.distinct,
+            subject,
+            shape: self.fields.iter().map(Field::to_shape_element).collect(),
+            filter: self.condition(),
+            orders: self.orders.iter().map(OrderBy::to_order).collect(),
+            offset: self.skip.filter(|n| *n > 0),
+            limit: self.take,
+        }))
+    }
 
-        // offset
-        if let Some(skip) = self.skip {
-            if skip > 0 {
-                q.push('\n');
-                push_str(q, "offset ", indent);
-                q.push_str(&skip.to_string());
-            }
-        }
+    fn validate(&self) -> Result<(), BuildError> {
+        let module = self.module.clone().map(With::module);
 
-        // limit
-        if let Some(take) = self.take {
-            q.push('\n');
-            push_str(q, "limit ", indent);
-            q.push_str(&take.to_string());
-        }
+        check_withs(module.iter().chain(&self.withs))?;
+        self.fields.iter().try_for_each(Field::validate)?;
+        self.filter.iter().try_for_each(Filter::validate)?;
 
-        qx
+        self.expr.iter().try_for_each(|x| x.validate())
     }
 }
 
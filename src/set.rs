@@ -0,0 +1,219 @@
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl SetOperator {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SetOperator::Union => "union",
+            SetOperator::Intersect => "intersect",
+            SetOperator::Except => "except",
+        }
+    }
+}
+
+/// `(a) union (b) except (c)`
+///
+/// operators are applied from left to right
+#[derive(Clone)]
+pub struct SetExpr<'a> {
+    first: Arc<dyn ToQuery + 'a>,
+    rest: Vec<(SetOperator, Arc<dyn ToQuery + 'a>)>,
+}
+
+pub fn union<'a, T, U>(a: T, b: U) -> SetExpr<'a>
+where
+    T: ToQuery + 'a,
+    U: ToQuery + 'a,
+{
+    SetExpr::new(a).union(b)
+}
+
+pub fn intersect<'a, T, U>(a: T, b: U) -> SetExpr<'a>
+where
+    T: ToQuery + 'a,
+    U: ToQuery + 'a,
+{
+    SetExpr::new(a).intersect(b)
+}
+
+pub fn except<'a, T, U>(a: T, b: U) -> SetExpr<'a>
+where
+    T: ToQuery + 'a,
+    U: ToQuery + 'a,
+{
+    SetExpr::new(a).except(b)
+}
+
+impl<'a> SetExpr<'a> {
+    pub fn new<T>(first: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self {
+            first: Arc::new(first),
+            rest: Vec::new(),
+        }
+    }
+
+    pub fn union<T>(self, x: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.push(SetOperator::Union, x)
+    }
+
+    pub fn intersect<T>(self, x: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.push(SetOperator::Intersect, x)
+    }
+
+    pub fn except<T>(self, x: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.push(SetOperator::Except, x)
+    }
+
+    fn push<T>(mut self, op: SetOperator, x: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        self.rest.push((op, Arc::new(x)));
+
+        self
+    }
+}
+
+impl<'a> ToQuery for SetExpr<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Set(Box::new(ast::Set {
+            first: self.first.to_ast(),
+            rest: self.rest.iter().map(|(op, x)| (*op, x.to_ast())).collect(),
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.first.validate()?;
+
+        self.rest.iter().try_for_each(|(_, x)| x.validate())
+    }
+}
+
+/// `distinct (expr)`
+#[derive(Clone)]
+pub struct Distinct<'a> {
+    expr: Arc<dyn ToQuery + 'a>,
+}
+
+pub fn distinct<'a, T>(expr: T) -> Distinct<'a>
+where
+    T: ToQuery + 'a,
+{
+    Distinct::new(expr)
+}
+
+impl<'a> Distinct<'a> {
+    pub fn new<T>(expr: T) -> Self
+    where
+        T: ToQuery + 'a,
+    {
+        Self {
+            expr: Arc::new(expr),
+        }
+    }
+}
+
+impl<'a> ToQuery for Distinct<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Prefix("distinct".into(), Box::new(self.expr.to_ast()))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        self.expr.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print() {
+        let novels = select("Novel", crate::fields! { title });
+        let comics = select("Comic", crate::fields! { title });
+        let banned = select("Book", crate::fields! { title }).filter(filter().add(
+            AND,
+            ".banned = $?",
+            true,
+        ));
+
+        let books = union(novels, comics.clone()).intersect(banned);
+
+        let query = select_expr(distinct(books.clone())).take(10).to_query();
+
+        assert_eq!(
+            query,
+            "\
+select (
+  distinct (
+    (
+      (
+        select Novel {
+          title,
+        }
+      ) union (
+        select Comic {
+          title,
+        }
+      )
+    ) intersect (
+      select Book {
+        title,
+      }
+      filter
+        .banned = true
+    )
+  )
+)
+limit 10"
+        );
+
+        let query = with_expr("books", books.except(comics)).to_query();
+
+        assert_eq!(
+            query,
+            "\
+books := (
+  (
+    (
+      select Novel {
+        title,
+      }
+    ) union (
+      select Comic {
+        title,
+      }
+    )
+  ) intersect (
+    select Book {
+      title,
+    }
+    filter
+      .banned = true
+  ) except (
+    select Comic {
+      title,
+    }
+  )
+)"
+        );
+    }
+}
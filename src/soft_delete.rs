@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+
+use super::*;
+
+/// soft-delete policy of a type, given by `TypeName::soft_delete`
+///
+/// ```ignore
+/// impl TypeName for Book {
+///     fn type_name() -> &'static str {
+///         "Book"
+///     }
+///
+///     fn soft_delete() -> Option<SoftDelete<'static>> {
+///         Some(soft_delete("deleted_at"))
+///     }
+/// }
+///
+/// // select Book filter not exists .deleted_at
+/// select_as::<Book>(fields! { title });
+///
+/// // update Book filter ... set { deleted_at := datetime_current() }
+/// delete_as::<Book>().filter(...);
+///
+/// // delete Book filter ...
+/// delete_as::<Book>().filter(...).with_deleted();
+/// ```
+#[derive(Clone)]
+pub struct SoftDelete<'a> {
+    property: Cow<'a, str>,
+    value: Cow<'a, str>,
+}
+
+pub fn soft_delete<'a>(property: impl Into<Cow<'a, str>>) -> SoftDelete<'a> {
+    SoftDelete::new(property)
+}
+
+impl<'a> SoftDelete<'a> {
+    pub fn new(property: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            property: property.into(),
+            value: Cow::Borrowed("datetime_current()"),
+        }
+    }
+
+    /// expression assigned on delete. default is `datetime_current()`
+    pub fn value(mut self, expr: impl Into<Cow<'a, str>>) -> Self {
+        self.value = expr.into();
+
+        self
+    }
+
+    pub fn property(&self) -> &str {
+        &self.property
+    }
+
+    /// `not exists .property and (filter)`
+    pub(crate) fn condition<'q>(&'q self, filter: Option<&'q Filter<'_>>) -> ast::Condition<'q> {
+        let not_deleted = ast::Term::Raw(format!("not exists .{}", self.property).into());
+
+        let mut terms = vec![(AND, not_deleted)];
+        terms.extend(
+            filter
+                .and_then(Filter::to_condition)
+                .map(|x| (AND, ast::Term::Group(x))),
+        );
+
+        ast::Condition {
+            not: false,
+            func: None,
+            terms,
+        }
+    }
+
+    /// `property := value`
+    pub(crate) fn assignment(&self) -> ast::Assignment<'_> {
+        ast::Assignment {
+            name: self.property.as_ref().into(),
+            assign: Assign::Replace,
+            value: ast::Expr::Raw(self.value.as_ref().into()),
+        }
+    }
+}
+
+/// filter of builder with soft-delete policy applied
+pub(crate) fn soft_deleted_condition<'q>(
+    filter: Option<&'q Filter<'_>>,
+    policy: Option<&'q SoftDelete<'_>>,
+) -> Option<ast::Condition<'q>> {
+    match policy {
+        Some(policy) => Some(policy.condition(filter)),
+        None => filter.and_then(Filter::to_condition),
+    }
+}
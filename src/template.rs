@@ -0,0 +1,529 @@
+use std::{borrow::Cow, sync::Arc};
+
+use edgedb_protocol::{
+    codec::{ObjectShape, ShapeElement},
+    common::Cardinality,
+    value::Value,
+};
+
+use super::*;
+
+/// slot markers are cut out of the rendered template. outside of a template they are refused by
+/// `try_to_query` and execution, so they never reach the database
+const SLOT_MARK: char = '\u{0}';
+
+/// named placeholder of `Template`. used anywhere a value or an expression goes
+///
+/// ```ignore
+/// let template = Template::new(
+///     select("Book", fields! { title }).filter(filter().add(AND, ".uid = $?", slot("uid"))),
+/// )?;
+///
+/// let books = template.bind("uid", 123)?.query::<Book>(&client).await?;
+/// ```
+#[derive(Clone)]
+pub struct Slot<'a>(Cow<'a, str>);
+
+pub fn slot<'a>(name: impl Into<Cow<'a, str>>) -> Slot<'a> {
+    Slot::new(name)
+}
+
+impl<'a> Slot<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>) -> Self {
+        Self(name.into())
+    }
+
+    fn mark(&self) -> String {
+        format!("{SLOT_MARK}{}{SLOT_MARK}", self.0)
+    }
+}
+
+impl<'a> ToQuery for Slot<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Raw(self.mark().into())
+    }
+}
+
+impl<'a> ToQueryArg for Slot<'a> {
+    fn to_query_arg(&self) -> String {
+        self.mark()
+    }
+}
+
+/// query built, validated and rendered once with named slots. bound values are passed as
+/// query arguments, so the query text is the same for every binding
+#[derive(Clone, Debug)]
+pub struct Template {
+    text: Arc<ast::Text>,
+    slots: Arc<[String]>,
+}
+
+impl Template {
+    /// validation and rendering are done here once, not on every bind
+    pub fn new(query: impl ToQuery) -> Result<Self, BuildError> {
+        query.validate()?;
+
+        let mut ast = query.to_ast();
+
+        split_literals(&mut ast);
+
+        let mut params = Vec::new();
+
+        collect_params(&mut ast, &mut params);
+
+        let pretty = printer::print(&ast, 0);
+
+        let slots = pretty
+            .split(SLOT_MARK)
+            .skip(1)
+            .step_by(2)
+            .map(str::to_string)
+            .collect();
+
+        let text = ast::Text {
+            pretty: parts(&pretty),
+            compact: parts(&printer::print_compact(&ast)),
+            redacted: parts(&printer::print_redacted(&ast)),
+            params,
+        };
+
+        Ok(Self {
+            text: Arc::new(text),
+            slots,
+        })
+    }
+
+    /// names of slots in order of appearance. repeated slots are listed repeatedly
+    pub fn slots(&self) -> impl Iterator<Item = &str> {
+        self.slots.iter().map(String::as_str)
+    }
+
+    /// `BuildError::NotArgument` if `value` cannot be a query argument
+    pub fn bind<'a, T>(
+        &self,
+        name: impl Into<Cow<'a, str>>,
+        value: T,
+    ) -> Result<Bound<'a>, BuildError>
+    where
+        T: ToQueryArg,
+    {
+        Bound {
+            template: self.clone(),
+            values: Vec::new(),
+        }
+        .bind(name, value)
+    }
+}
+
+/// text around the slot marks
+fn parts(text: &str) -> Vec<String> {
+    text.split(SLOT_MARK)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+/// slots in literals become separate marks, so they are kept in the redacted layout
+fn split_literals(expr: &mut ast::Expr<'_>) {
+    let ast::Expr::Literal(text) = &*expr else {
+        expr.visit_children_mut(&mut |x| split_literals(x));
+
+        return;
+    };
+
+    if !text.contains(SLOT_MARK) {
+        return;
+    }
+
+    let parts = text
+        .split(SLOT_MARK)
+        .enumerate()
+        .filter(|(_, x)| !x.is_empty())
+        .map(|(i, x)| match i % 2 {
+            0 => ast::Expr::Literal(x.to_string().into()),
+            _ => ast::Expr::Raw(Slot::new(x).mark().into()),
+        })
+        .collect();
+
+    *expr = ast::Expr::Seq(parts);
+}
+
+/// `Template` with values. executed like other builders
+#[derive(Clone)]
+pub struct Bound<'a> {
+    template: Template,
+    /// name, type and value of each argument
+    values: Vec<(Cow<'a, str>, Cow<'static, str>, Value)>,
+}
+
+impl<'a> Bound<'a> {
+    /// binding the same name again replaces the value.
+    /// `BuildError::NotArgument` if `value` cannot be a query argument
+    pub fn bind<T>(mut self, name: impl Into<Cow<'a, str>>, value: T) -> Result<Self, BuildError>
+    where
+        T: ToQueryArg,
+    {
+        let name = name.into();
+
+        let Some((ty, value)) = value.to_query_param() else {
+            return Err(BuildError::NotArgument {
+                name: name.into_owned(),
+            });
+        };
+
+        self.values.retain(|(x, ..)| *x != name);
+        self.values.push((name, ty, value));
+
+        Ok(self)
+    }
+
+    fn value(&self, name: &str) -> Option<(&Cow<'static, str>, &Value)> {
+        self.values
+            .iter()
+            .find_map(|(x, ty, value)| (x == name).then_some((ty, value)))
+    }
+}
+
+impl<'a> ToQuery for Bound<'a> {
+    /// the rendered text with `<type>$name` at each slot
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let slots = self
+            .template
+            .slots()
+            .map(|name| match self.value(name) {
+                Some((ty, value)) => Either::Right(ast::Param {
+                    name: name.into(),
+                    ty: ty.clone(),
+                    value: value.clone(),
+                }),
+                // reported by `validate`
+                None => Either::Left(name.into()),
+            })
+            .collect();
+
+        ast::Expr::Rendered(Box::new(ast::Rendered {
+            text: Cow::Borrowed(&self.template.text),
+            slots,
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        if let Some(name) = self.template.slots().find(|x| self.value(x).is_none()) {
+            return Err(BuildError::UnboundSlot {
+                name: name.to_string(),
+            });
+        }
+
+        if let Some((name, ..)) = self
+            .values
+            .iter()
+            .find(|(x, ..)| !self.template.slots().any(|slot| slot == x))
+        {
+            return Err(BuildError::UnknownSlot {
+                name: name.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// named arguments of the parameters in `expr`, in order of appearance. `None` if there are none.
+/// `BuildError::StraySlot` if a slot is left outside of a template
+pub(crate) fn arguments(expr: &mut ast::Expr<'_>) -> Result<Option<Value>, BuildError> {
+    if let Some(name) = stray_slot(expr) {
+        return Err(BuildError::StraySlot { name });
+    }
+
+    let mut params = Vec::new();
+
+    collect_params(expr, &mut params);
+
+    let mut names: Vec<String> = Vec::new();
+    let mut fields: Vec<Option<Value>> = Vec::new();
+
+    for param in params {
+        match names.iter().position(|x| *x == param.name) {
+            Some(i) if fields[i].as_ref() == Some(&param.value) => {}
+            Some(_) => {
+                return Err(BuildError::ConflictingSlot {
+                    name: param.name.into_owned(),
+                })
+            }
+            None => {
+                names.push(param.name.into_owned());
+                fields.push(Some(param.value));
+            }
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(None);
+    }
+
+    let elements = names
+        .into_iter()
+        .map(|name| ShapeElement {
+            flag_implicit: false,
+            flag_link_property: false,
+            flag_link: false,
+            cardinality: Some(Cardinality::One),
+            name,
+        })
+        .collect();
+
+    Ok(Some(Value::Object {
+        shape: ObjectShape::new(elements),
+        fields,
+    }))
+}
+
+/// name of the first slot mark in the text of `expr`
+fn stray_slot(expr: &mut ast::Expr<'_>) -> Option<String> {
+    if let ast::Expr::Raw(text) | ast::Expr::Literal(text) = &*expr {
+        return text.split(SLOT_MARK).nth(1).map(str::to_string);
+    }
+
+    let mut name = None;
+
+    expr.visit_children_mut(&mut |x| {
+        if name.is_none() {
+            name = stray_slot(x);
+        }
+    });
+
+    name
+}
+
+fn collect_params(expr: &mut ast::Expr<'_>, params: &mut Vec<ast::Param<'static>>) {
+    match expr {
+        ast::Expr::Param(x) => params.push(ast::Param::clone(x).into_owned()),
+        ast::Expr::Rendered(x) => {
+            params.extend(x.text.params.iter().cloned());
+            params.extend(
+                x.slots
+                    .iter()
+                    .filter_map(|x| x.as_ref().right())
+                    .map(|x| x.clone().into_owned()),
+            );
+        }
+        _ => expr.visit_children_mut(&mut |x| collect_params(x, params)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgedb_tokio::errors::{ClientError, ErrorKind};
+
+    use super::*;
+
+    #[test]
+    fn print() {
+        let template = Template::new(
+            select("Book", crate::fields! { uid, title })
+                .filter(filter().add(AND, ".uid = $?", slot("uid")).add(
+                    AND,
+                    ".title = $?",
+                    slot("title"),
+                ))
+                .take(10),
+        )
+        .unwrap();
+
+        let bound = template
+            .bind("uid", 123)
+            .and_then(|x| x.bind("title", "it's"))
+            .unwrap();
+
+        let query = bound.to_query();
+        let compact = bound.to_query_compact();
+
+        assert_eq!(
+            query,
+            "\
+select Book {
+  uid,
+  title,
+}
+filter
+  .uid = <int32>$uid and
+  .title = <str>$title
+limit 10"
+        );
+        assert_eq!(
+            compact,
+            "select Book {uid, title} filter .uid = <int32>$uid and .title = <str>$title limit 10"
+        );
+
+        // values are arguments, so the text is the same for other values
+        assert_eq!(
+            template
+                .bind("uid", 1)
+                .and_then(|x| x.bind("title", "other"))
+                .unwrap()
+                .to_query(),
+            query
+        );
+
+        let Some(Value::Object { fields, .. }) = Prepared::new(&bound).unwrap().args else {
+            panic!("bound values are not arguments");
+        };
+
+        assert_eq!(
+            fields,
+            [
+                Some(Value::Int32(123)),
+                Some(Value::Str("it's".to_string()))
+            ]
+        );
+
+        let prepared = Prepared::new(&bound).unwrap();
+
+        let Error::Query(err) = prepared.error(ClientError::with_message("failed")) else {
+            panic!("not a query error");
+        };
+
+        assert!(err.args().is_some());
+        assert_eq!(err.args(), prepared.args.as_ref());
+
+        assert_eq!(
+            template.bind("uid", 1).unwrap().try_to_query(),
+            Err(BuildError::UnboundSlot {
+                name: "title".to_string()
+            })
+        );
+
+        let name = Template::new(str_lower(slot("name"))).unwrap();
+        let both = func("contains")
+            .arg_expr(name.bind("name", "a").unwrap())
+            .arg_expr(name.bind("name", "b").unwrap());
+
+        assert_eq!(
+            Prepared::new(&both).err(),
+            Some(BuildError::ConflictingSlot {
+                name: "name".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn arguments() {
+        let template = Template::new(select("Book", crate::fields! { uid }).filter(
+            filter().add(AND, ".uid in $?", slot("uids")).add(
+                AND,
+                "(.title, .rating) = $?",
+                slot("pair"),
+            ),
+        ))
+        .unwrap();
+
+        let bound = template
+            .bind("uids", vec![1u32, 2])
+            .and_then(|x| x.bind("pair", ("a", 1i8)))
+            .unwrap();
+
+        assert_eq!(
+            bound.to_query_compact(),
+            "select Book {uid} filter .uid in array_unpack(<array<int64>>$uids) and (.title, .rating) = <tuple<str, int16>>$pair"
+        );
+
+        let Some(Value::Object { fields, .. }) = Prepared::new(&bound).unwrap().args else {
+            panic!("bound values are not arguments");
+        };
+
+        assert_eq!(
+            fields,
+            [
+                Some(Value::Array(vec![Value::Int64(1), Value::Int64(2)])),
+                Some(Value::Tuple(vec![
+                    Value::Str("a".to_string()),
+                    Value::Int16(1)
+                ])),
+            ]
+        );
+
+        for value in [
+            template.bind("uids", u64::MAX).err(),
+            template.bind("uids", Vec::<i32>::new()).err(),
+            template.bind("uids", vec![vec![1]]).err(),
+            template.bind("uids", raw("{1, 2}")).err(),
+        ] {
+            assert_eq!(
+                value,
+                Some(BuildError::NotArgument {
+                    name: "uids".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let template = Template::new(
+            select("Book", crate::fields! { uid }).filter(
+                filter()
+                    .add(AND, ".author = $?", slot("author"))
+                    .add(AND, ".title = $?", "secret"),
+            ),
+        )
+        .unwrap();
+
+        let bound = template.bind("author", "kim").unwrap();
+
+        let query = select("Author", crate::fields! { name })
+            .filter(filter().add_expr(AND, exists(bound.clone())))
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+select Author {
+  name,
+}
+filter
+  exists (
+    select Book {
+      uid,
+    }
+    filter
+      .author = <str>$author and
+      .title = <str>'secret'
+  )"
+        );
+
+        let redacted = printer::print_redacted(&bound.to_ast());
+
+        assert_eq!(
+            redacted,
+            "\
+select Book {
+  uid,
+}
+filter
+  .author = <str>$author and
+  .title = $?"
+        );
+    }
+
+    #[test]
+    fn stray() {
+        let query = select("Book", crate::fields! { uid }).filter(filter().add(
+            AND,
+            ".uid = $?",
+            slot("uid"),
+        ));
+
+        assert_eq!(
+            query.try_to_query(),
+            Err(BuildError::StraySlot {
+                name: "uid".to_string()
+            })
+        );
+        assert_eq!(
+            Prepared::new(&str_lower(slot("name"))).err(),
+            Some(BuildError::StraySlot {
+                name: "name".to_string()
+            })
+        );
+    }
+}
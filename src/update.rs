@@ -1,25 +1,79 @@
+use std::borrow::Cow;
+
 use either::Either;
 
 use super::*;
 
 #[derive(Clone)]
 pub struct UpdateBuilder<'a> {
-    target: &'a str,
+    target: Cow<'a, str>,
     withs: Vec<With<'a>>,
     filter: Option<Filter<'a>>,
-    values: Vec<(&'a str, Assign, QueryArgOrExpr<'a>)>,
+    all: bool,
+    soft_delete: Option<SoftDelete<'a>>,
+    with_deleted: bool,
+    values: Vec<(Cow<'a, str>, Assign, QueryArgOrExpr<'a>)>,
+    /// field and type
+    casts: Vec<(Cow<'a, str>, Cow<'a, str>)>,
 }
 
-pub fn update(target: &str) -> UpdateBuilder {
+pub fn update<'a>(target: impl Into<Cow<'a, str>>) -> UpdateBuilder<'a> {
     UpdateBuilder::new(target)
 }
 
+/// target is `T::type_name()`. soft-deleted objects are excluded by `T::soft_delete()`
+pub fn update_as<'a, T>() -> UpdateBuilder<'a>
+where
+    T: TypeName,
+{
+    let mut update = UpdateBuilder::new(T::type_name());
+
+    update.soft_delete = T::soft_delete();
+
+    update
+}
+
+/// `update Type` assigning only fields changed from `original` to `modified`, filtered by `id`.
+/// `BuildError::InvalidPayload` if `original` has no `id`
+///
+/// ```ignore
+/// let mut modified = book.clone();
+/// modified.title = "new title".to_string();
+///
+/// let query = update_diff(&book, &modified)?;
+///
+/// if !query.is_empty() {
+///     query.execute(&client).await?;
+/// }
+/// ```
+#[cfg(feature = "serde")]
+pub fn update_diff<T>(original: &T, modified: &T) -> Result<UpdateBuilder<'static>, BuildError>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let diff = payload::diff(original, modified)?;
+
+    let Some(id) = diff.id else {
+        return Err(BuildError::InvalidPayload {
+            message: "original has no id".to_string(),
+        });
+    };
+
+    let update = UpdateBuilder::new(diff.name).filter(filter().add(AND, ".id = $?", raw(id)));
+
+    Ok(update.push_diff(diff.assignments))
+}
+
 impl<'a> UpdateBuilder<'a> {
-    pub fn new(target: &'a str) -> Self {
+    pub fn new(target: impl Into<Cow<'a, str>>) -> Self {
         Self {
-            target,
+            target: target.into(),
             filter: None,
+            all: false,
+            soft_delete: None,
+            with_deleted: false,
             values: Vec::new(),
+            casts: Vec::new(),
             withs: Vec::new(),
         }
     }
@@ -36,17 +90,37 @@ impl<'a> UpdateBuilder<'a> {
         self
     }
 
-    pub fn set<T>(mut self, field: &'a str, assign: Assign, value: T) -> Self
+    /// update soft-deleted objects too
+    pub fn with_deleted(mut self) -> Self {
+        self.with_deleted = true;
+
+        self
+    }
+
+    /// allow update without filter. otherwise `try_to_query` refuses it
+    /// and `to_query` renders `filter false`
+    pub fn all(mut self) -> Self {
+        self.all = true;
+
+        self
+    }
+
+    pub fn set<T>(mut self, field: impl Into<Cow<'a, str>>, assign: Assign, value: T) -> Self
     where
         T: ToQueryArg + 'a,
     {
         self.values
-            .push((field, assign, Either::Left(Box::new(value))));
+            .push((field.into(), assign, Either::Left(Arc::new(value))));
 
         self
     }
 
-    pub fn set_opt<T>(self, field: &'a str, assign: Assign, opt_value: Option<T>) -> Self
+    pub fn set_opt<T>(
+        self,
+        field: impl Into<Cow<'a, str>>,
+        assign: Assign,
+        opt_value: Option<T>,
+    ) -> Self
     where
         T: ToQueryArg + 'a,
     {
@@ -57,53 +131,150 @@ impl<'a> UpdateBuilder<'a> {
         }
     }
 
-    pub fn set_expr<T>(mut self, field: &'a str, assign: Assign, expr: T) -> Self
+    pub fn set_expr<T>(mut self, field: impl Into<Cow<'a, str>>, assign: Assign, expr: T) -> Self
     where
         T: ToQuery + 'a,
     {
         self.values
-            .push((field, assign, Either::Right(Box::new(expr))));
+            .push((field.into(), assign, Either::Right(Arc::new(expr))));
 
         self
     }
-}
 
-impl<'a> ToQuery for UpdateBuilder<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
+    /// `<ty>` before the value of `field`. e.g. `datetime` or `uuid` for strings of a struct
+    /// payload, which are `<str>` otherwise. `author.born` is a field of an inserted `author`
+    pub fn cast(mut self, field: impl Into<Cow<'a, str>>, ty: impl Into<Cow<'a, str>>) -> Self {
+        self.casts.push((field.into(), ty.into()));
+
+        self
+    }
 
-        // with
-        {
-            push_withs(q, &self.withs, indent);
+    /// every field of struct `value` replaced. see `payload` for the mapping
+    #[cfg(feature = "serde")]
+    pub fn set_struct<T>(mut self, value: &T, nulls: payload::Nulls) -> Result<Self, BuildError>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let (_, values) = payload::assignments(value, nulls)?;
+
+        for (field, value) in values {
+            self = match value {
+                Either::Left(value) => self.set(field, Assign::Replace, value),
+                Either::Right(expr) => self.set_expr(field, Assign::Replace, expr),
+            };
         }
 
-        // update
-        {
-            push_str(q, "update", indent);
-            q.push(' ');
+        Ok(self)
+    }
+
+    /// fields changed from `original` to `modified`. see `update_diff`
+    #[cfg(feature = "serde")]
+    pub fn set_diff<T>(self, original: &T, modified: &T) -> Result<Self, BuildError>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let diff = payload::diff(original, modified)?;
+
+        Ok(self.push_diff(diff.assignments))
+    }
 
-            q.push_str(self.target);
+    #[cfg(feature = "serde")]
+    fn push_diff(mut self, values: Vec<(&'static str, Assign, payload::FieldValue)>) -> Self {
+        for (field, assign, value) in values {
+            self = match value {
+                Either::Left(value) => self.set(field, assign, value),
+                Either::Right(expr) => self.set_expr(field, assign, expr),
+            };
         }
 
-        q.push('\n');
+        self
+    }
 
-        // filter
-        {
-            push_filter(q, self.filter.as_ref(), indent);
+    /// RFC 7396 merge patch limited to `fields`. see `patch` for the mapping
+    #[cfg(feature = "serde")]
+    pub fn apply_patch(
+        mut self,
+        patch: &serde_json::Value,
+        fields: &PatchFields,
+    ) -> Result<Self, BuildError> {
+        for (field, value) in fields.assignments(patch, "")? {
+            self = match value {
+                Either::Left(value) => self.set(field, Assign::Replace, value),
+                Either::Right(expr) => self.set_expr(field, Assign::Replace, expr),
+            };
         }
 
-        q.push('\n');
+        Ok(self)
+    }
 
-        push_str(q, "set", indent);
-        q.push(' ');
+    /// no assignment. e.g. nothing changed in `update_diff`
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
 
-        // set values
-        {
-            push_object(q, &self.values, indent);
+    fn filtered(&self) -> bool {
+        self.filter
+            .as_ref()
+            .is_some_and(|filter| !filter.is_empty())
+    }
+
+    fn condition(&self) -> Option<ast::Condition<'_>> {
+        if !self.filtered() && !self.all {
+            return Some(refused_filter());
         }
 
-        qx
+        let policy = self.soft_delete.as_ref().filter(|_| !self.with_deleted);
+
+        soft_deleted_condition(self.filter.as_ref(), policy)
+    }
+}
+
+impl<'a> ToQuery for UpdateBuilder<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        let mut assignments: Vec<_> = self
+            .values
+            .iter()
+            .map(|(field, assign, value)| ast::Assignment {
+                name: field.as_ref().into(),
+                assign: *assign,
+                value: arg_or_expr_ast(value),
+            })
+            .collect();
+
+        for (field, ty) in &self.casts {
+            cast_assignment(&mut assignments, field, ty);
+        }
+
+        ast::Expr::Update(Box::new(ast::Update {
+            withs: self.withs.iter().map(With::to_with_item).collect(),
+            subject: ast::Expr::Raw(self.target.as_ref().into()),
+            filter: self.condition(),
+            assignments,
+        }))
+    }
+
+    fn validate(&self) -> Result<(), BuildError> {
+        check_withs(&self.withs)?;
+        self.filter.iter().try_for_each(Filter::validate)?;
+
+        for (_, _, value) in &self.values {
+            validate_arg_or_expr(value)?;
+        }
+
+        if !self.filtered() && !self.all {
+            return Err(BuildError::Unfiltered {
+                statement: "update",
+                target: self.target.to_string(),
+            });
+        }
+
+        if self.values.is_empty() {
+            return Err(BuildError::NoValues {
+                target: self.target.to_string(),
+            });
+        }
+
+        Ok(())
     }
 }
 
@@ -120,4 +291,84 @@ mod tests {
 
         println!("{query}");
     }
+
+    #[test]
+    fn unfiltered() {
+        let query = update("Book").set("released", Assign::Replace, true);
+
+        assert_eq!(
+            query.try_to_query(),
+            Err(BuildError::Unfiltered {
+                statement: "update",
+                target: "Book".to_string(),
+            })
+        );
+        assert_eq!(
+            query.to_query(),
+            "\
+update Book
+filter
+  false
+set {
+  released := true,
+}"
+        );
+        assert_eq!(
+            query.all().to_query(),
+            "\
+update Book
+set {
+  released := true,
+}"
+        );
+
+        assert_eq!(
+            delete("Book").try_to_query(),
+            Err(BuildError::Unfiltered {
+                statement: "delete",
+                target: "Book".to_string(),
+            })
+        );
+        assert_eq!(delete("Book").to_query(), "delete Book\nfilter\n  false");
+        assert_eq!(delete("Book").all().to_query(), "delete Book");
+    }
+
+    #[test]
+    fn owned() {
+        fn build(target: String, field: String) -> UpdateBuilder<'static> {
+            update(target)
+                .with(with_module(String::from("library")))
+                .filter(filter().add(AND, format!(".{field} = $?"), String::from("old")))
+                .set(field, Assign::Replace, String::from("new"))
+        }
+
+        let query = build("Book".to_string(), "title".to_string());
+
+        let handle = std::thread::spawn(move || query.to_query());
+
+        let query = handle.join().unwrap();
+
+        assert_eq!(
+            query,
+            "\
+with
+  module library,
+update Book
+filter
+  .title = <str>'old'
+set {
+  title := <str>'new',
+}"
+        );
+    }
+
+    #[test]
+    fn no_values() {
+        assert_eq!(
+            update("Book").all().try_to_query(),
+            Err(BuildError::NoValues {
+                target: "Book".to_string()
+            })
+        );
+    }
 }
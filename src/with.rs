@@ -1,23 +1,73 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
 use either::Either;
 
-use super::{push_str, ToQuery, ToQueryArg};
+use super::{
+    arg_or_expr_ast, ast, validate_arg_or_expr, BuildError, QueryArgOrExpr, ToQuery, ToQueryArg,
+};
+
+static UNIQUE_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone)]
+enum Declaration<'a> {
+    /// name := value
+    Binding(Option<QueryArgOrExpr<'a>>),
+    /// [name as] module module
+    Module(Cow<'a, str>),
+}
 
 #[derive(Clone)]
 pub struct With<'a> {
-    pub name: &'a str,
-    x: Option<Either<Box<dyn ToQueryArg + 'a>, Box<dyn ToQuery + 'a>>>,
+    /// empty if `module module`
+    pub name: Cow<'a, str>,
+    declaration: Declaration<'a>,
 }
 
 impl<'a> With<'a> {
-    pub fn new(name: &'a str) -> Self {
-        Self { name, x: None }
+    pub fn new(name: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: name.into(),
+            declaration: Declaration::Binding(None),
+        }
+    }
+
+    /// `module module`
+    pub fn module(module: impl Into<Cow<'a, str>>) -> Self {
+        Self::module_alias("", module)
+    }
+
+    /// `alias as module module`
+    pub fn module_alias(alias: impl Into<Cow<'a, str>>, module: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            name: alias.into(),
+            declaration: Declaration::Module(module.into()),
+        }
+    }
+
+    /// append unique suffix to name. e.g. `tags` -> `tags_3`
+    pub fn unique(mut self) -> Self {
+        self.name = Cow::Owned(unique_name(&self.name));
+
+        self
+    }
+
+    /// reference to this binding for other builders
+    pub fn handle(&self) -> WithName<'a> {
+        WithName(self.name.clone())
     }
 
     pub fn expr<T>(mut self, expr: T) -> Self
     where
         T: ToQuery + 'a,
     {
-        self.x.replace(Either::Right(Box::new(expr)));
+        self.declaration = Declaration::Binding(Some(Either::Right(Arc::new(expr))));
 
         self
     }
@@ -26,49 +76,232 @@ impl<'a> With<'a> {
     where
         T: ToQueryArg + 'a,
     {
-        self.x.replace(Either::Left(Box::new(value)));
+        self.declaration = Declaration::Binding(Some(Either::Left(Arc::new(value))));
 
         self
     }
 }
 
-pub fn with<'a, T>(name: &'a str, value: T) -> With<'a>
+pub fn with<'a, T>(name: impl Into<Cow<'a, str>>, value: T) -> With<'a>
 where
     T: ToQueryArg + 'a,
 {
     With::new(name).value(value)
 }
 
-pub fn with_expr<'a, T>(name: &'a str, expr: T) -> With<'a>
+pub fn with_expr<'a, T>(name: impl Into<Cow<'a, str>>, expr: T) -> With<'a>
 where
     T: ToQuery + 'a,
 {
     With::new(name).expr(expr)
 }
 
+pub fn with_module<'a>(module: impl Into<Cow<'a, str>>) -> With<'a> {
+    With::module(module)
+}
+
+pub fn with_module_alias<'a>(
+    alias: impl Into<Cow<'a, str>>,
+    module: impl Into<Cow<'a, str>>,
+) -> With<'a> {
+    With::module_alias(alias, module)
+}
+
+impl<'a> With<'a> {
+    /// an unset binding is written as `name := {}`. `validate` reports it as
+    /// `BuildError::UnsetWith`
+    pub(crate) fn to_with_item(&self) -> ast::WithItem<'_> {
+        match &self.declaration {
+            Declaration::Binding(x) => ast::WithItem::Binding {
+                name: self.name.as_ref().into(),
+                value: match x {
+                    Some(x) => arg_or_expr_ast(x),
+                    None => ast::Expr::Raw("{}".into()),
+                },
+            },
+            Declaration::Module(module) => ast::WithItem::Module {
+                alias: Some(self.name.as_ref().into()).filter(|x: &Cow<str>| !x.is_empty()),
+                module: module.as_ref().into(),
+            },
+        }
+    }
+}
+
 impl<'a> ToQuery for With<'a> {
-    fn to_query_with_indent(&self, indent: usize) -> String {
-        let mut qx = String::new();
-        let q = &mut qx;
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::WithItem(Box::new(self.to_with_item()))
+    }
 
-        push_str(q, self.name, indent);
-        q.push_str(" := ");
+    fn validate(&self) -> Result<(), BuildError> {
+        check_withs([self])
+    }
+}
 
-        match self.x.as_ref().expect("not set value from With") {
-            Either::Left(value) => {
-                q.push_str(&value.to_query_arg());
-            }
-            Either::Right(expr) => {
-                q.push('(');
-                q.push('\n');
+/// `prefix_N`
+pub(crate) fn unique_name(prefix: &str) -> String {
+    let id = UNIQUE_ID.fetch_add(1, Ordering::Relaxed);
+
+    format!("{prefix}_{id}")
+}
+
+/// name of `With` binding
+#[derive(Clone)]
+pub struct WithName<'a>(Cow<'a, str>);
+
+impl<'a> WithName<'a> {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> fmt::Display for WithName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'a> From<WithName<'a>> for Cow<'a, str> {
+    fn from(name: WithName<'a>) -> Self {
+        name.0
+    }
+}
+
+impl<'a> ToQuery for WithName<'a> {
+    fn to_ast(&self) -> ast::Expr<'_> {
+        ast::Expr::Raw(self.0.as_ref().into())
+    }
+}
+
+impl<'a> ToQueryArg for WithName<'a> {
+    fn to_query_arg(&self) -> String {
+        self.0.to_string()
+    }
+}
 
-                q.push_str(&expr.to_query_with_indent(2 + indent));
+/// `BuildError::UnsetWith` if a binding has no value,
+/// `BuildError::DuplicateWith` if a name is bound twice or `module module` is declared twice
+/// in one with block. bound expressions are validated too
+pub(crate) fn check_withs<'a>(
+    withs: impl IntoIterator<Item = &'a With<'a>>,
+) -> Result<(), BuildError> {
+    let mut names = Vec::new();
 
-                q.push('\n');
-                push_str(q, ")", indent);
+    for with in withs {
+        match &with.declaration {
+            Declaration::Binding(Some(value)) => validate_arg_or_expr(value)?,
+            Declaration::Binding(None) => {
+                return Err(BuildError::UnsetWith {
+                    name: with.name.to_string(),
+                })
             }
+            Declaration::Module(_) => {}
         }
 
-        qx
+        // `module module` has no name, so it is keyed by the keyword
+        let name = match with.name.is_empty() {
+            true => Cow::Borrowed("module"),
+            false => with.name.clone(),
+        };
+
+        if names.contains(&name) {
+            return Err(BuildError::DuplicateWith {
+                name: name.to_string(),
+            });
+        }
+
+        names.push(name);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn print() {
+        let query = select("Book", crate::fields! { title, author: { name } })
+            .module("library")
+            .with(with_module_alias("ext", "external"))
+            .with(with_expr(
+                "authors",
+                select("Author", []).with(with_module("people")),
+            ))
+            .filter(filter().add(AND, ".author in $?", raw("authors")))
+            .to_query();
+
+        assert_eq!(
+            query,
+            "\
+with
+  module library,
+  ext as module external,
+  authors := (
+    with
+      module people,
+    select Author
+  ),
+select Book {
+  title,
+  author: {
+    name,
+  },
+}
+filter
+  .author in authors"
+        );
+    }
+
+    #[test]
+    fn duplicate() {
+        let tags = with_expr("tags", select("BookTag", [])).unique();
+        let other_tags = with_expr("tags", select("BookTag", [])).unique();
+
+        assert_ne!(tags.handle().as_str(), other_tags.handle().as_str());
+
+        let query = select("Book", crate::fields! { title })
+            .filter(filter().add(AND, ".tags in $?", tags.handle()))
+            .with(tags.clone());
+
+        assert!(query.clone().with(other_tags).try_to_query().is_ok());
+
+        assert_eq!(
+            query.with(tags.clone()).try_to_query(),
+            Err(BuildError::DuplicateWith {
+                name: tags.handle().to_string()
+            })
+        );
+
+        let query = select("Book", crate::fields! { title }).module("a");
+
+        assert_eq!(
+            query.clone().with(with_module("b")).try_to_query(),
+            Err(BuildError::DuplicateWith {
+                name: "module".to_string()
+            })
+        );
+        assert_eq!(
+            query.with(with_module_alias("b", "b")).to_query(),
+            "\
+with
+  module a,
+  b as module b,
+select Book {
+  title,
+}"
+        );
+    }
+
+    #[test]
+    fn unset() {
+        let query = select("Book", crate::fields! { title }).with(With::new("tags"));
+
+        assert_eq!(
+            query.try_to_query(),
+            Err(BuildError::UnsetWith {
+                name: "tags".to_string()
+            })
+        );
     }
 }